@@ -0,0 +1,61 @@
+//! Coldkey account lookups and TAO formatting.
+
+use crate::error::RegbotError;
+use scale_value::{At, Value};
+use subxt::utils::{AccountId32, H256};
+use subxt::{OnlineClient, SubstrateConfig};
+
+/// Number of rao in one TAO
+pub const RAO_PER_TAO: u128 = 1_000_000_000;
+
+/// Formats an amount of rao as TAO, e.g. `1.500000000 τ`
+pub fn format_tao(rao: u128) -> String {
+    format!("{}.{:09} τ", rao / RAO_PER_TAO, rao % RAO_PER_TAO)
+}
+
+/// The nonce and free balance of an account, as stored in `System.Account`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountState {
+    /// Nonce of the account's next extrinsic, counting only included ones
+    pub nonce: u64,
+    /// Free balance in rao
+    pub free: u128,
+}
+
+/// Retrieves the nonce and free balance of an account at a specific block
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `account` - The account to look up
+/// * `block_hash` - The hash of the block whose storage should be read
+///
+/// # Returns
+///
+/// A `Result` containing the `AccountState` (zeroed for unknown accounts), or an `Err` if retrieval fails
+pub async fn get_account(
+    client: &OnlineClient<SubstrateConfig>,
+    account: &AccountId32,
+    block_hash: H256,
+) -> Result<AccountState, RegbotError> {
+    let account_key =
+        subxt::storage::dynamic("System", "Account", vec![Value::from_bytes(account.0)]);
+    let Some(account_info) = client.storage().at(block_hash).fetch(&account_key).await? else {
+        return Ok(AccountState::default());
+    };
+
+    let account_info = account_info.to_value()?;
+    let nonce = account_info
+        .at("nonce")
+        .and_then(|nonce| nonce.as_u128())
+        .ok_or("System.Account has no nonce")?;
+    let free = account_info
+        .at("data")
+        .at("free")
+        .and_then(|free| free.as_u128())
+        .ok_or("System.Account has no data.free balance")?;
+    Ok(AccountState {
+        nonce: nonce as u64,
+        free,
+    })
+}
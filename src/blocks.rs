@@ -0,0 +1,117 @@
+//! Sources of new blocks driving the slot logic: polling or new-head subscriptions.
+
+use crate::error::RegbotError;
+use clap::ValueEnum;
+use log::{info, warn};
+use std::time::{Duration, Instant};
+use subxt::backend::StreamOfResults;
+use subxt::blocks::Block;
+use subxt::{OnlineClient, SubstrateConfig};
+
+/// A block of the chain we register on
+pub type ChainBlock = Block<SubstrateConfig, OnlineClient<SubstrateConfig>>;
+
+/// Delay between two polls of the latest block
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A subscription without a new block for this long is considered stalled
+const SUBSCRIPTION_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to poll after a stalled subscription before subscribing again
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where the bot learns about new blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BlockSource {
+    /// New best-block notifications (`subscribe_best`)
+    SubscribeBest,
+    /// New finalized-block notifications (`subscribe_finalized`)
+    SubscribeFinalized,
+    /// Fetch the latest block every 500 ms
+    Poll,
+}
+
+/// Yields new blocks from the configured source, falling back to polling while a subscription is stalled
+pub struct BlockTracker {
+    client: OnlineClient<SubstrateConfig>,
+    source: BlockSource,
+    subscription: Option<StreamOfResults<ChainBlock>>,
+    /// When polling because the subscription failed, the time to try subscribing again
+    resubscribe_at: Option<Instant>,
+}
+
+impl BlockTracker {
+    /// Creates a tracker; subscriptions are opened lazily on the first call to `next`
+    pub fn new(client: OnlineClient<SubstrateConfig>, source: BlockSource) -> Self {
+        Self {
+            client,
+            source,
+            subscription: None,
+            resubscribe_at: None,
+        }
+    }
+
+    /// Waits for the next block from the current source
+    ///
+    /// In poll mode the same block may be returned several times; callers deduplicate by block number.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the block, or an `Err` if it could not be fetched; callers should simply call again
+    pub async fn next(&mut self) -> Result<ChainBlock, RegbotError> {
+        if self.source != BlockSource::Poll && self.subscription.is_none() {
+            let due = self
+                .resubscribe_at
+                .is_none_or(|resubscribe_at| Instant::now() >= resubscribe_at);
+            if due {
+                self.subscribe().await;
+            }
+        }
+
+        if let Some(subscription) = &mut self.subscription {
+            match tokio::time::timeout(SUBSCRIPTION_STALL_TIMEOUT, subscription.next()).await {
+                Ok(Some(Ok(block))) => return Ok(block),
+                Ok(Some(Err(e))) => warn!("Block subscription failed: {:?}", e),
+                Ok(None) => warn!("Block subscription ended"),
+                Err(_) => warn!(
+                    "Block subscription stalled for {:?}",
+                    SUBSCRIPTION_STALL_TIMEOUT
+                ),
+            }
+            warn!(
+                "📉 Falling back to polling, will subscribe again in {:?}",
+                RESUBSCRIBE_INTERVAL
+            );
+            self.subscription = None;
+            self.resubscribe_at = Some(Instant::now() + RESUBSCRIBE_INTERVAL);
+        }
+
+        // Small delay to prevent overwhelming the RPC
+        tokio::time::sleep(POLL_INTERVAL).await;
+        Ok(self.client.blocks().at_latest().await?)
+    }
+
+    /// Opens the configured subscription, leaving the tracker in polling mode if that fails
+    async fn subscribe(&mut self) {
+        let subscription = match self.source {
+            BlockSource::SubscribeBest => self.client.blocks().subscribe_best().await,
+            BlockSource::SubscribeFinalized => self.client.blocks().subscribe_finalized().await,
+            BlockSource::Poll => return,
+        };
+
+        match subscription {
+            Ok(subscription) => {
+                info!("📡 Subscribed to {:?} block notifications", self.source);
+                self.subscription = Some(subscription);
+                self.resubscribe_at = None;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to subscribe to blocks, polling for {:?}: {:?}",
+                    RESUBSCRIBE_INTERVAL, e
+                );
+                self.resubscribe_at = Some(Instant::now() + RESUBSCRIBE_INTERVAL);
+            }
+        }
+    }
+}
@@ -0,0 +1,602 @@
+//! Registration parameters and their loading from command line arguments and TOML config files.
+
+use crate::blocks::BlockSource;
+use crate::secret::SecretString;
+use clap::builder::Resettable;
+use clap::{Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Field of `RegistrationParams` holding a secret parameter
+type SecretField = fn(&mut RegistrationParams) -> &mut Vec<SecretString>;
+
+/// Parameters holding secrets; their config file values never go through the argument list as plain strings
+const SECRET_KEYS: [(&str, SecretField); 2] = [
+    ("coldkey", |params| &mut params.coldkey),
+    ("hotkey", |params| &mut params.hotkey),
+];
+
+/// Secrets of a config file, with the fields receiving them once the arguments are parsed
+type FileSecrets = Vec<(SecretField, Vec<SecretString>)>;
+
+/// Value standing in for a secret from the config file in the argument list, so clap still sees the flag
+const SECRET_PLACEHOLDER: &str = "<from config file>";
+
+/// Struct to hold registration parameters, can be parsed from command line or config file
+#[derive(Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_override_self = true
+)]
+pub struct RegistrationParams {
+    /// Path to a TOML config file providing any of these parameters; command line flags override its values
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// Coldkey secret URI; prefer `--wallet-name` so the seed stays out of shell history.
+    /// Repeat to register several pairs from one process; a single coldkey pays for every hotkey.
+    #[clap(long, required_unless_present = "wallet_name")]
+    pub coldkey: Vec<SecretString>,
+
+    /// Hotkey as an SS58 address or `pub:0x`-prefixed hex public key, so the hotkey secret never has to be on this
+    /// host. A secret URI, including a bare `0x` hex seed, is still accepted. Repeat to register several pairs, matched to the coldkeys in order.
+    #[clap(long, required_unless_present_any = ["wallet_name", "hotkeys_file"])]
+    pub hotkey: Vec<SecretString>,
+
+    /// Name of the btcli wallet to load the coldkey (and hotkey) from, used when `--coldkey` / `--hotkey` are not given.
+    /// Encrypted coldkeys are unlocked with the password in `REGBOT_COLDKEY_PASSWORD`, or prompted for.
+    /// Repeat to register hotkeys of several wallets.
+    #[clap(long)]
+    pub wallet_name: Vec<String>,
+
+    /// Name of the hotkey within the wallet; repeat to register several hotkeys of the same wallet
+    #[clap(long, default_value = "default")]
+    pub wallet_hotkey: Vec<String>,
+
+    /// File listing hotkeys to register one after another, one per line: an SS58 address, `wallet:<wallet>/<hotkey>`
+    /// for a btcli hotkey, or a secret URI. Each coldkey moves on to the next hotkey after a confirmed registration.
+    #[clap(long, conflicts_with_all = ["hotkey", "wallet_hotkey"])]
+    pub hotkeys_file: Option<PathBuf>,
+
+    /// File keeping the progress of `--hotkeys-file` (pending, registered UID or failed per hotkey), so a restart
+    /// resumes where it left off. Defaults to the hotkeys file with `.state.json` appended.
+    #[clap(long, requires = "hotkeys_file")]
+    pub hotkeys_state: Option<PathBuf>,
+
+    /// Generate this many fresh hotkeys in the `--wallet-name` wallet and register them one after another.
+    /// Each coldkey writes its next hotkey as a btcli keyfile (`regbot-<n>`) right before registering it.
+    #[clap(long, requires = "wallet_name", conflicts_with_all = ["hotkey", "wallet_hotkey", "hotkeys_file"])]
+    pub auto_hotkey: Option<usize>,
+
+    /// Directory containing btcli wallets
+    #[clap(long, default_value = "~/.bittensor/wallets")]
+    pub wallet_path: String,
+
+    #[clap(long)]
+    pub netuid: u16,
+
+    /// Maximum cost (in rao) we are willing to pay per registration: the burn plus the tip plus the fee.
+    /// Slots where the on-chain burn leaves no room for the fee are skipped, and the tip is cut to fit.
+    #[clap(long, default_value = "5000000000")]
+    pub max_cost: u64,
+
+    /// Tip (in rao) paid with each registration extrinsic, to be picked first when registrations compete for a block
+    #[clap(long, default_value = "0")]
+    pub tip: u64,
+
+    /// Amount (in rao) the tip is raised by after each slot we submitted on without getting registered
+    #[clap(long, default_value = "0", requires = "max_tip")]
+    pub tip_step: u64,
+
+    /// Highest tip (in rao) to escalate to with `--tip-step`; defaults to `--tip`
+    #[clap(long)]
+    pub max_tip: Option<u64>,
+
+    /// Keep waiting when the coldkey cannot pay the burn plus fee, instead of exiting
+    #[clap(long)]
+    pub wait_for_funds: bool,
+
+    /// RPC endpoints, comma separated or repeated. Each is health-checked (latency, best block, lag behind
+    /// the others) and the freshest one is used, failing over when it falls behind or errors.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        num_args = 1..,
+        default_value = "wss://entrypoint-finney.opentensor.ai:443"
+    )]
+    pub chain_endpoint: Vec<String>,
+
+    /// Push each signed extrinsic to every configured endpoint in parallel; the first acceptance counts
+    #[clap(long)]
+    pub broadcast: bool,
+
+    /// Follow each submitted extrinsic through the pool (Ready/Broadcast/InBlock/Finalized/Dropped/Invalid)
+    /// in the background and log its final outcome
+    #[clap(long)]
+    pub watch_submissions: bool,
+
+    /// Where new blocks come from: new-head subscriptions (best or finalized), or polling every 500 ms.
+    /// Subscriptions fall back to polling while they are stalled.
+    #[clap(long, value_enum, default_value = "poll")]
+    pub block_source: BlockSource,
+
+    /// Modulus of the slot schedule: a block's slot is `block_number % slot_modulus`
+    #[clap(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    pub slot_modulus: u32,
+
+    /// Slots to submit on, comma separated or repeated, each below `--slot-modulus`.
+    /// - `--slots 0`: submits on blocks where block_number % 3 == 0
+    /// - `--slot-modulus 5 --slots 0,2`: submits on blocks where block_number % 5 is 0 or 2
+    ///
+    /// With several key pairs the slots are dealt out in turn, e.g. 3 pairs with `--slots 0,1,2` get one slot each
+    /// and register 3 miners per epoch from one process.
+    #[clap(
+        long,
+        alias = "slot",
+        value_delimiter = ',',
+        num_args = 1..,
+        default_value = "0"
+    )]
+    pub slots: Vec<u32>,
+
+    /// Submit on every block, ignoring `--slot-modulus` and `--slots`; every key pair submits on every block
+    #[clap(long, conflicts_with_all = ["slot_modulus", "slots"])]
+    pub every_block: bool,
+
+    /// Mortality of each registration extrinsic, in blocks from the block observed when signing it (rounded up to a
+    /// power of two, at least 4). A submission that misses its slot expires instead of landing later at another burn.
+    #[clap(long, default_value = "32", value_parser = clap::value_parser!(u64).range(4..=65536))]
+    pub mortality_blocks: u64,
+}
+
+/// Commands run instead of the registration
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Generate hotkeys in a btcli wallet, write their keyfiles and exit
+    Keygen(KeygenParams),
+}
+
+/// Parameters of `regbot keygen`
+#[derive(Args, Debug)]
+pub struct KeygenParams {
+    /// Name of the wallet receiving the hotkeys
+    #[clap(long)]
+    pub wallet_name: String,
+
+    /// Names of the hotkeys to generate; repeat for several
+    #[clap(long = "hotkey", required = true)]
+    pub hotkeys: Vec<String>,
+
+    /// Directory containing btcli wallets
+    #[clap(long, default_value = "~/.bittensor/wallets")]
+    pub wallet_path: String,
+}
+
+/// What the command line asks the bot to do
+#[derive(Debug)]
+pub enum Mode {
+    /// Register hotkeys
+    Register(Box<RegistrationParams>),
+    /// Run a subcommand instead
+    Subcommand(Subcommands),
+}
+
+/// Parses configuration from either a config file or command line arguments
+///
+/// When `--config` is given, every key of the TOML file is turned into the matching command line flag
+/// and placed before the real arguments, so flags given on the command line take precedence.
+/// Invalid command lines, `--help` and `--version` print their message and exit, like any clap parser.
+///
+/// # Returns
+///
+/// A `Result` containing the `Mode` if parsing is successful, or an `Err` if the config file is invalid
+pub fn parse_config() -> Result<Mode, Box<dyn std::error::Error>> {
+    parse_args(std::env::args_os().collect()).map_err(|e| match e.downcast::<clap::Error>() {
+        Ok(e) => e.exit(),
+        Err(e) => e,
+    })
+}
+
+/// Parses the given command line, merged with the config file it names
+///
+/// # Arguments
+///
+/// * `cli_args` - The command line arguments, starting with the binary name
+///
+/// # Returns
+///
+/// A `Result` containing the `Mode`, or an `Err` if the command line or config file is invalid
+fn parse_args(mut cli_args: Vec<OsString>) -> Result<Mode, Box<dyn std::error::Error>> {
+    let binary = if cli_args.is_empty() {
+        OsString::from(env!("CARGO_PKG_NAME"))
+    } else {
+        cli_args.remove(0)
+    };
+
+    let mut args = vec![binary];
+    let mut secrets = Vec::new();
+    if let Some(path) = find_config_path(&cli_args) {
+        let (file_args, file_secrets) = config_file_args(&path, &cli_args)?;
+        args.extend(file_args);
+        secrets = file_secrets;
+    }
+    args.extend(cli_args);
+
+    let matches = command().try_get_matches_from(args)?;
+    if matches.subcommand().is_some() {
+        return Ok(Mode::Subcommand(Subcommands::from_arg_matches(&matches)?));
+    }
+    let mut params = RegistrationParams::from_arg_matches(&matches)?;
+    for (field, values) in secrets {
+        *field(&mut params) = values;
+    }
+    Ok(Mode::Register(Box::new(params)))
+}
+
+/// Builds the command line parser: the registration parameters, or a subcommand without them
+fn command() -> Command {
+    let registration = RegistrationParams::command();
+    // Augmenting replaces the description with the one of `Subcommands`
+    let about = registration.get_about().cloned();
+    Subcommands::augment_subcommands(registration)
+        .about(about.map_or(Resettable::Reset, Resettable::Value))
+        .long_about(None)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+}
+
+/// Finds the value of `--config` among raw command line arguments
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments, without the binary name
+///
+/// # Returns
+///
+/// The config file path if `--config` was given
+fn find_config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Converts a TOML config file into command line arguments
+///
+/// Keys are the `RegistrationParams` field names (e.g. `max_cost`) or their aliases. Keys also given on the command line
+/// are skipped, and every value is validated against its flag so errors point at the offending key. Secrets are
+/// moved out of the file into `SecretString`s and only a placeholder goes into the arguments.
+///
+/// # Arguments
+///
+/// * `path` - Path to the TOML config file
+/// * `cli_args` - The command line arguments, used to detect overridden keys
+///
+/// # Returns
+///
+/// A `Result` containing the arguments equivalent to the file with the secrets to put in place of their
+/// placeholders, or an `Err` describing the invalid key
+fn config_file_args(
+    path: &Path,
+    cli_args: &[OsString],
+) -> Result<(Vec<OsString>, FileSecrets), Box<dyn std::error::Error>> {
+    let display = path.display();
+    // The file may hold secret URIs, so wipe the raw contents once parsed
+    let contents = std::fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|e| format!("Failed to read config file {}: {}", display, e))?;
+    let mut table: toml::Table =
+        toml::from_str(&contents).map_err(|e| format!("Invalid config file {}: {}", display, e))?;
+
+    // Take the secrets out first, so they are wiped whatever happens to the rest of the file
+    let mut file_secrets = Vec::new();
+    for (key, field) in SECRET_KEYS {
+        if let Some(value) = table.remove(key) {
+            file_secrets.push((key, field, secret_values(value)));
+        }
+    }
+
+    let command = RegistrationParams::command();
+    // Used to validate one key at a time, so nothing else can be required; the relations between keys are
+    // checked when the merged arguments are parsed
+    let mut validator = command.clone().mut_args(|arg| {
+        arg.required(false)
+            .required_unless_present(Resettable::Reset)
+            .requires(Resettable::Reset)
+    });
+    let mut args = Vec::new();
+    let mut secrets = Vec::new();
+
+    for (key, field, values) in file_secrets {
+        let values = values.map_err(|e| {
+            format!(
+                "Invalid value for key `{}` in config file {}: {}",
+                key, display, e
+            )
+        })?;
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key)
+            .ok_or_else(|| format!("Unknown key `{}` in config file {}", key, display))?;
+        if overridden(arg, cli_args) {
+            continue;
+        }
+        let flag = format!("--{}={}", key, SECRET_PLACEHOLDER);
+        args.extend(values.iter().map(|_| OsString::from(&flag)));
+        secrets.push((field, values));
+    }
+
+    for (key, value) in &table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| {
+                let aliased = arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&key.as_str()));
+                (arg.get_id() == key.as_str() || aliased) && key != "config"
+            })
+            .ok_or_else(|| format!("Unknown key `{}` in config file {}", key, display))?;
+        let flag = format!("--{}", arg.get_long().unwrap_or(key));
+        if overridden(arg, cli_args) {
+            continue;
+        }
+
+        // Flags without values are switched on by `key = true`
+        if !arg.get_action().takes_values() {
+            match value {
+                toml::Value::Boolean(true) => args.push(OsString::from(&flag)),
+                toml::Value::Boolean(false) => {}
+                _ => {
+                    return Err(format!(
+                        "Invalid value for key `{}` in config file {}: expected a boolean",
+                        key, display
+                    )
+                    .into())
+                }
+            }
+            continue;
+        }
+
+        let values = match value {
+            toml::Value::Array(items) => items.iter().collect(),
+            _ => vec![value],
+        };
+        for value in values {
+            let raw = match value {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => {
+                    return Err(format!(
+                        "Invalid value for key `{}` in config file {}: unsupported value type",
+                        key, display
+                    )
+                    .into())
+                }
+            };
+            let flag_value = format!("{}={}", flag, raw);
+            validator
+                .try_get_matches_from_mut([env!("CARGO_PKG_NAME"), flag_value.as_str()])
+                .map_err(|e| {
+                    // Keep the whole reason, without the usage that follows it
+                    let message = e.to_string();
+                    let reason = message.split("\n\n").next().unwrap_or_default();
+                    format!(
+                        "Invalid value for key `{}` in config file {}: {}",
+                        key,
+                        display,
+                        reason.trim_start_matches("error: ").trim_end()
+                    )
+                })?;
+            args.push(OsString::from(flag_value));
+        }
+    }
+
+    Ok((args, secrets))
+}
+
+/// Returns whether the command line gives a parameter, under its long name or any of its aliases
+///
+/// # Arguments
+///
+/// * `arg` - The parameter
+/// * `cli_args` - The command line arguments
+///
+/// # Returns
+///
+/// `true` if the command line value takes precedence over the config file
+fn overridden(arg: &clap::Arg, cli_args: &[OsString]) -> bool {
+    let flags: Vec<String> = arg
+        .get_long()
+        .into_iter()
+        .chain(arg.get_all_aliases().unwrap_or_default())
+        .map(|name| format!("--{}", name))
+        .collect();
+    cli_args.iter().any(|cli_arg| {
+        let cli_arg = cli_arg.to_string_lossy();
+        flags.iter().any(|flag| {
+            cli_arg == *flag
+                || cli_arg
+                    .strip_prefix(flag.as_str())
+                    .is_some_and(|rest| rest.starts_with('='))
+        })
+    })
+}
+
+/// Moves the strings of a secret config file value into `SecretString`s
+///
+/// # Arguments
+///
+/// * `value` - A string or an array of strings
+///
+/// # Returns
+///
+/// A `Result` containing the secrets, or an `Err` if a value is not a string
+fn secret_values(value: toml::Value) -> Result<Vec<SecretString>, String> {
+    let values = match value {
+        toml::Value::Array(items) => items,
+        value => vec![value],
+    };
+
+    // Wrap every string before reporting an invalid item, so none is left behind unwiped
+    let mut secrets = Vec::with_capacity(values.len());
+    let mut invalid = false;
+    for value in values {
+        match value {
+            toml::Value::String(secret) => secrets.push(SecretString::from(secret)),
+            _ => invalid = true,
+        }
+    }
+    if invalid {
+        return Err("expected a string or an array of strings".to_string());
+    }
+    Ok(secrets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `args` with a config file holding `contents`, named after the test so tests can run in parallel
+    fn parse_with_file(name: &str, contents: &str, args: &[&str]) -> Result<Mode, String> {
+        let path =
+            std::env::temp_dir().join(format!("regbot-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        let mut cli_args: Vec<OsString> =
+            vec!["regbot".into(), "--config".into(), path.clone().into()];
+        cli_args.extend(args.iter().map(OsString::from));
+        let mode = parse_args(cli_args).map_err(|e| e.to_string());
+        let _ = std::fs::remove_file(path);
+        mode
+    }
+
+    fn registration(mode: Result<Mode, String>) -> RegistrationParams {
+        match mode {
+            Ok(Mode::Register(params)) => *params,
+            other => panic!("expected registration parameters, got {:?}", other),
+        }
+    }
+
+    const KEYS: &str = "coldkey = \"//Alice\"\nhotkey = \"//Bob\"\n";
+
+    #[test]
+    fn command_line_overrides_file() {
+        let contents = format!("{}netuid = 1\nmax_cost = 10\n", KEYS);
+        let params = registration(parse_with_file("precedence", &contents, &["--netuid", "2"]));
+        assert_eq!(params.netuid, 2);
+        assert_eq!(params.max_cost, 10);
+    }
+
+    #[test]
+    fn alias_overrides_file_key() {
+        let contents = format!("{}netuid = 1\nslots = [1, 2]\n", KEYS);
+        let params = registration(parse_with_file("alias-cli", &contents, &["--slot", "0"]));
+        assert_eq!(params.slots, vec![0]);
+        let params = registration(parse_with_file("alias-cli-eq", &contents, &["--slot=2"]));
+        assert_eq!(params.slots, vec![2]);
+    }
+
+    #[test]
+    fn file_key_may_be_an_alias() {
+        let contents = format!("{}netuid = 1\nslot = [1, 2]\n", KEYS);
+        let params = registration(parse_with_file("alias-file", &contents, &[]));
+        assert_eq!(params.slots, vec![1, 2]);
+    }
+
+    #[test]
+    fn file_keys_may_require_other_keys() {
+        let contents = format!("{}netuid = 1\ntip_step = 5\nmax_tip = 100\n", KEYS);
+        let params = registration(parse_with_file("requires-tip", &contents, &[]));
+        assert_eq!((params.tip_step, params.max_tip), (5, Some(100)));
+
+        let contents = "coldkey = \"//Alice\"\nnetuid = 1\nhotkeys_file = \"hotkeys.txt\"\n\
+                        hotkeys_state = \"state.json\"\n";
+        let params = registration(parse_with_file("requires-state", contents, &[]));
+        assert_eq!(params.hotkeys_state, Some(PathBuf::from("state.json")));
+
+        let contents =
+            "coldkey = \"//Alice\"\nnetuid = 1\nwallet_name = \"miner\"\nauto_hotkey = 2\n";
+        let params = registration(parse_with_file("requires-auto", contents, &[]));
+        assert_eq!(params.auto_hotkey, Some(2));
+    }
+
+    #[test]
+    fn requirement_met_on_the_command_line() {
+        let contents = format!("{}netuid = 1\ntip_step = 5\n", KEYS);
+        let params = registration(parse_with_file(
+            "requires-cli",
+            &contents,
+            &["--max-tip", "100"],
+        ));
+        assert_eq!(params.max_tip, Some(100));
+    }
+
+    #[test]
+    fn unmet_requirement_is_rejected() {
+        let contents = format!("{}netuid = 1\ntip_step = 5\n", KEYS);
+        let error = parse_with_file("requires-unmet", &contents, &[]).unwrap_err();
+        assert!(error.contains("--max-tip"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_and_unknown_keys_are_rejected() {
+        let contents = format!("{}netuid = 1\nmax_cost = \"a lot\"\n", KEYS);
+        let error = parse_with_file("invalid", &contents, &[]).unwrap_err();
+        assert!(error.contains("`max_cost`"), "{}", error);
+
+        let contents = format!("{}netuid = 1\nmaxcost = 5\n", KEYS);
+        let error = parse_with_file("unknown", &contents, &[]).unwrap_err();
+        assert!(error.contains("Unknown key `maxcost`"), "{}", error);
+    }
+
+    #[test]
+    fn secrets_come_from_the_file_unless_overridden() {
+        let contents = format!("{}netuid = 1\n", KEYS);
+        let params = registration(parse_with_file("secrets", &contents, &[]));
+        assert_eq!(params.coldkey[0].expose_secret(), "//Alice");
+        assert_eq!(params.hotkey[0].expose_secret(), "//Bob");
+
+        let params = registration(parse_with_file(
+            "secrets-cli",
+            &contents,
+            &["--coldkey", "//Eve"],
+        ));
+        assert_eq!(params.coldkey.len(), 1);
+        assert_eq!(params.coldkey[0].expose_secret(), "//Eve");
+        assert_eq!(params.hotkey[0].expose_secret(), "//Bob");
+    }
+
+    #[test]
+    fn keygen_is_a_subcommand() {
+        let args = [
+            "regbot",
+            "keygen",
+            "--wallet-name",
+            "miner",
+            "--hotkey",
+            "a",
+        ];
+        match parse_args(args.iter().map(OsString::from).collect()) {
+            Ok(Mode::Subcommand(Subcommands::Keygen(keygen))) => {
+                assert_eq!(keygen.wallet_name, "miner");
+                assert_eq!(keygen.hotkeys, vec!["a".to_string()]);
+            }
+            other => panic!(
+                "expected keygen, got {:?}",
+                other.map_err(|e| e.to_string())
+            ),
+        }
+    }
+}
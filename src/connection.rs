@@ -0,0 +1,334 @@
+//! Connections to one or more RPC endpoints, with health ranking, failover and reconnection with
+//! exponential backoff when a websocket drops.
+
+use crate::error::RegbotError;
+use futures::future::join_all;
+use log::{debug, info, warn};
+use std::time::{Duration, Instant};
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::RpcClient;
+use subxt::utils::H256;
+use subxt::{OnlineClient, SubstrateConfig};
+use tokio::task::JoinHandle;
+
+/// Delay before the first reconnection attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound for the delay between reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Time allowed for connecting to an endpoint or answering a health probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An open connection to a node
+#[derive(Clone)]
+pub struct Connection {
+    /// High level client used for storage, blocks and transactions
+    pub client: OnlineClient<SubstrateConfig>,
+    /// Raw RPC methods on the same websocket
+    pub rpc: LegacyRpcMethods<SubstrateConfig>,
+}
+
+impl Connection {
+    /// Opens a websocket to a node and fetches its metadata
+    async fn open(url: &str) -> Result<Self, RegbotError> {
+        let rpc_client = RpcClient::from_url(url).await?;
+        let client = OnlineClient::<SubstrateConfig>::from_rpc_client(rpc_client.clone()).await?;
+        Ok(Self {
+            client,
+            rpc: LegacyRpcMethods::new(rpc_client),
+        })
+    }
+}
+
+/// Result of the last health probe of an endpoint
+#[derive(Debug, Clone, Copy)]
+struct EndpointHealth {
+    /// Round trip time of a `chain_getHeader` request
+    latency: Duration,
+    /// Best block height reported by the endpoint
+    best_block: u32,
+}
+
+/// A configured endpoint and its current state
+struct Endpoint {
+    url: String,
+    connection: Option<Connection>,
+    /// `None` when the last probe failed
+    health: Option<EndpointHealth>,
+}
+
+impl Endpoint {
+    /// Connects if needed and measures latency and best block height, recording the result in `health`
+    async fn probe(&mut self) {
+        let result = tokio::time::timeout(PROBE_TIMEOUT, async {
+            let connection = match &self.connection {
+                Some(connection) => connection.clone(),
+                None => Connection::open(&self.url).await?,
+            };
+            let start = Instant::now();
+            let header = connection
+                .rpc
+                .chain_get_header(None)
+                .await?
+                .ok_or_else(|| RegbotError::Rpc("node returned no best header".to_string()))?;
+            let health = EndpointHealth {
+                latency: start.elapsed(),
+                best_block: header.number,
+            };
+            Ok::<_, RegbotError>((connection, health))
+        })
+        .await;
+
+        match result {
+            Ok(Ok((connection, health))) => {
+                self.connection = Some(connection);
+                self.health = Some(health);
+            }
+            Ok(Err(e)) => {
+                warn!("Health check of {} failed: {}", self.url, e);
+                self.connection = None;
+                self.health = None;
+            }
+            Err(_) => {
+                warn!(
+                    "Health check of {} timed out after {:?}",
+                    self.url, PROBE_TIMEOUT
+                );
+                self.connection = None;
+                self.health = None;
+            }
+        }
+    }
+}
+
+/// Owns the connections to all configured endpoints and selects the freshest one for block tracking
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    active: usize,
+    /// Connection of the active endpoint, kept even when a health probe of that endpoint fails
+    connection: Connection,
+    reconnects: u64,
+}
+
+impl EndpointPool {
+    /// Connects to all endpoints and selects the freshest; the initial connection is not retried so
+    /// misconfigurations fail fast
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - The websocket URLs of the nodes
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the pool, or an `Err` if no endpoint can be reached
+    pub async fn connect(urls: &[String]) -> Result<Self, RegbotError> {
+        let mut endpoints: Vec<Endpoint> = urls
+            .iter()
+            .map(|url| Endpoint {
+                url: url.clone(),
+                connection: None,
+                health: None,
+            })
+            .collect();
+
+        join_all(endpoints.iter_mut().map(Endpoint::probe)).await;
+        let Some((active, connection)) = rank(&endpoints, 0) else {
+            return Err(RegbotError::Rpc(format!(
+                "none of the endpoints could be reached: {}",
+                urls.join(", ")
+            )));
+        };
+        let pool = Self {
+            endpoints,
+            active,
+            connection,
+            reconnects: 0,
+        };
+        info!("🔌 Using endpoint {}", pool.active_url());
+
+        Ok(pool)
+    }
+
+    /// Returns a handle to the active connection's client
+    pub fn client(&self) -> OnlineClient<SubstrateConfig> {
+        self.connection.client.clone()
+    }
+
+    /// Returns a handle to the active connection's raw RPC methods
+    pub fn rpc(&self) -> LegacyRpcMethods<SubstrateConfig> {
+        self.connection.rpc.clone()
+    }
+
+    /// Returns the URL of the active endpoint
+    pub fn active_url(&self) -> &str {
+        &self.endpoints[self.active].url
+    }
+
+    /// Returns the number of configured endpoints
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Probes every endpoint and switches to a fresher one if the active endpoint lags or failed
+    ///
+    /// # Returns
+    ///
+    /// `true` if the active endpoint changed and clients must be refreshed
+    pub async fn check_health(&mut self) -> bool {
+        let previous = self.active;
+        self.probe_all().await;
+        if !self.select() {
+            warn!(
+                "No endpoint passed the health check, keeping {}",
+                self.active_url()
+            );
+            return false;
+        }
+        if self.active != previous {
+            info!(
+                "🔀 Failing over from {} to {}",
+                self.endpoints[previous].url,
+                self.active_url()
+            );
+        }
+        self.active != previous
+    }
+
+    /// Drops the active connection and fails over to the best reachable endpoint, retrying with
+    /// exponential backoff until one is reachable
+    pub async fn reconnect(&mut self) {
+        let lost = self.active_url().to_string();
+        self.endpoints[self.active].connection = None;
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt: u32 = 0;
+
+        loop {
+            attempt += 1;
+            self.probe_all().await;
+            if self.select() {
+                self.reconnects += 1;
+                info!(
+                    "🔌 Reconnected to {} after losing {} ({} attempts, {} reconnects so far)",
+                    self.active_url(),
+                    lost,
+                    attempt,
+                    self.reconnects
+                );
+                return;
+            }
+
+            warn!(
+                "🔌 No endpoint reachable (attempt {}), retrying in {:?}",
+                attempt, backoff
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Submits already signed extrinsic bytes to every connected endpoint except the active one
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded` - The SCALE encoded signed extrinsic
+    ///
+    /// # Returns
+    ///
+    /// The spawned `author_submitExtrinsic` calls, labelled with their endpoint URL
+    pub fn spawn_submissions(
+        &self,
+        encoded: &[u8],
+    ) -> Vec<(String, JoinHandle<Result<H256, RegbotError>>)> {
+        self.endpoints
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != self.active)
+            .filter_map(|(_, endpoint)| {
+                let rpc = endpoint.connection.as_ref()?.rpc.clone();
+                let encoded = encoded.to_vec();
+                let handle =
+                    tokio::spawn(async move { Ok(rpc.author_submit_extrinsic(&encoded).await?) });
+                Some((endpoint.url.clone(), handle))
+            })
+            .collect()
+    }
+
+    /// Probes all endpoints concurrently
+    async fn probe_all(&mut self) {
+        join_all(self.endpoints.iter_mut().map(Endpoint::probe)).await;
+    }
+
+    /// Makes the best healthy endpoint active, see `rank`
+    ///
+    /// # Returns
+    ///
+    /// `false` if no endpoint is healthy, in which case the active connection is kept
+    fn select(&mut self) -> bool {
+        match rank(&self.endpoints, self.active) {
+            Some((active, connection)) => {
+                self.active = active;
+                self.connection = connection;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Ranks healthy endpoints by lag behind the highest best block, then latency, and picks the best one unless
+/// the active endpoint is itself fully caught up
+///
+/// # Arguments
+///
+/// * `endpoints` - The configured endpoints with their last probe results
+/// * `active` - The index of the active endpoint
+///
+/// # Returns
+///
+/// The index and connection of the endpoint to use, or `None` if no endpoint is healthy
+fn rank(endpoints: &[Endpoint], active: usize) -> Option<(usize, Connection)> {
+    let healthy: Vec<(usize, EndpointHealth, &Connection)> = endpoints
+        .iter()
+        .enumerate()
+        .filter_map(|(index, endpoint)| {
+            Some((index, endpoint.health?, endpoint.connection.as_ref()?))
+        })
+        .collect();
+    let highest = healthy
+        .iter()
+        .map(|(_, health, _)| health.best_block)
+        .max()?;
+
+    let mut ranking: Vec<(usize, u32, Duration, &Connection)> = healthy
+        .into_iter()
+        .map(|(index, health, connection)| {
+            (
+                index,
+                highest - health.best_block,
+                health.latency,
+                connection,
+            )
+        })
+        .collect();
+    ranking.sort_by_key(|&(_, lag, latency, _)| (lag, latency));
+
+    for &(index, lag, latency, _) in &ranking {
+        debug!(
+            "🩺 {} - best block {}, {} blocks behind, latency {:?}",
+            endpoints[index].url,
+            highest - lag,
+            lag,
+            latency
+        );
+    }
+
+    // Avoid flapping between endpoints that are equally fresh
+    let chosen = ranking
+        .iter()
+        .find(|&&(index, lag, _, _)| index == active && lag == 0)
+        .or_else(|| ranking.first())?;
+
+    Some((chosen.0, chosen.3.clone()))
+}
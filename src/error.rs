@@ -0,0 +1,478 @@
+//! Typed errors for the registration bot, with their retry policies and process exit codes.
+
+use crate::balance::format_tao;
+use jsonrpsee::core::ClientError;
+use std::fmt;
+use subxt::error::{DecodeError, DispatchError, RpcError};
+
+/// JSON-RPC error code for transactions rejected by the pool as invalid
+const POOL_INVALID_TX: i32 = 1010;
+/// JSON-RPC error code for transactions whose validity could not be determined
+const POOL_UNKNOWN_VALIDITY: i32 = 1011;
+/// JSON-RPC error code for transactions already present in the pool
+const POOL_ALREADY_IMPORTED: i32 = 1013;
+/// JSON-RPC error code for transactions replacing one with a higher priority
+const POOL_TOO_LOW_PRIORITY: i32 = 1014;
+
+/// What the registration loop should do after an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Try again on the next matching slot
+    NextSlot,
+    /// Give up; retrying cannot succeed without operator intervention
+    Abort,
+}
+
+/// Reasons the transaction pool reports a transaction as invalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidTransaction {
+    /// The nonce was already used (`Transaction is outdated`)
+    Stale,
+    /// The nonce is ahead of the account's next nonce
+    Future,
+    /// The account cannot pay the transaction fees
+    Payment,
+    /// The transaction would not fit in the block
+    ExhaustsResources,
+    /// The signature does not match the signer
+    BadProof,
+    /// The mortality checkpoint block is no longer known
+    AncientBirthBlock,
+    /// A runtime-specific validity error code
+    Custom(u8),
+    /// Any other validity error, as reported by the node
+    Other(String),
+}
+
+impl InvalidTransaction {
+    /// Maps the message the node sends for an invalid transaction to its variant
+    fn from_message(message: &str) -> Self {
+        match message {
+            "Transaction is outdated" => Self::Stale,
+            "Transaction will be valid in the future" => Self::Future,
+            "Inability to pay some fees (e.g. account balance too low)" => Self::Payment,
+            "Transaction would exhaust the block limits" => Self::ExhaustsResources,
+            "Transaction has a bad signature" => Self::BadProof,
+            "Transaction has an ancient birth block" => Self::AncientBirthBlock,
+            _ => match message
+                .strip_prefix("Custom error: ")
+                .and_then(|code| code.parse().ok())
+            {
+                Some(code) => Self::Custom(code),
+                None => Self::Other(message.to_string()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for InvalidTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stale => f.write_str("stale nonce"),
+            Self::Future => f.write_str("nonce from the future"),
+            Self::Payment => f.write_str("cannot pay fees"),
+            Self::ExhaustsResources => f.write_str("exhausts block resources"),
+            Self::BadProof => f.write_str("bad signature"),
+            Self::AncientBirthBlock => f.write_str("ancient birth block"),
+            Self::Custom(code) => write!(f, "custom error {}", code),
+            Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Errors produced while registering a hotkey
+#[derive(Debug)]
+pub enum RegbotError {
+    /// Invalid parameters, config file or key material
+    Config(String),
+    /// The coldkey cannot pay the burn plus tip and fee (amounts in rao)
+    InsufficientBalance { free: u128, required: u128 },
+    /// The RPC connection failed or a request could not be completed
+    Rpc(String),
+    /// The connection to the node was lost and must be re-established
+    Disconnected(String),
+    /// The transaction pool rejected the transaction as invalid
+    InvalidTransaction(InvalidTransaction),
+    /// The transaction pool refused the transaction for a non-validity reason (duplicate, low priority, ...)
+    PoolRejected { code: i32, message: String },
+    /// The extrinsic was dispatched and failed with a pallet error, decoded against the runtime metadata
+    Dispatch {
+        pallet: String,
+        error: String,
+        docs: String,
+    },
+    /// The extrinsic was dispatched and failed with a non-pallet runtime error
+    Runtime(String),
+    /// Any other failure, such as missing storage or undecodable data
+    Other(String),
+}
+
+impl RegbotError {
+    /// Returns whether the registration loop should retry after this error
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match self {
+            Self::Config(_) => RetryPolicy::Abort,
+            Self::InsufficientBalance { .. } => RetryPolicy::Abort,
+            Self::Rpc(_) | Self::Disconnected(_) => RetryPolicy::NextSlot,
+            Self::InvalidTransaction(reason) => match reason {
+                InvalidTransaction::Stale
+                | InvalidTransaction::Future
+                | InvalidTransaction::ExhaustsResources
+                | InvalidTransaction::AncientBirthBlock
+                | InvalidTransaction::Custom(_) => RetryPolicy::NextSlot,
+                InvalidTransaction::Payment
+                | InvalidTransaction::BadProof
+                | InvalidTransaction::Other(_) => RetryPolicy::Abort,
+            },
+            Self::PoolRejected { .. } => RetryPolicy::NextSlot,
+            Self::Dispatch { error, .. } => match error.as_str() {
+                "TooManyRegistrationsThisBlock" | "TooManyRegistrationsThisInterval" => {
+                    RetryPolicy::NextSlot
+                }
+                // The registration check before the next slot ends the run cleanly
+                "HotKeyAlreadyRegisteredInSubNet" => RetryPolicy::NextSlot,
+                _ => RetryPolicy::Abort,
+            },
+            Self::Runtime(_) => RetryPolicy::NextSlot,
+            Self::Other(_) => RetryPolicy::NextSlot,
+        }
+    }
+
+    /// Returns whether the connection to the node is gone and must be re-established
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self, Self::Disconnected(_))
+    }
+
+    /// Returns whether the pool rejected the nonce as already used or too far ahead
+    pub fn is_bad_nonce(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidTransaction(InvalidTransaction::Stale | InvalidTransaction::Future)
+        )
+    }
+
+    /// Returns whether the pool already holds this exact transaction, e.g. from a broadcast to another node
+    pub fn is_already_imported(&self) -> bool {
+        matches!(
+            self,
+            Self::PoolRejected {
+                code: POOL_ALREADY_IMPORTED,
+                ..
+            }
+        )
+    }
+
+    /// Returns whether the pool holds another transaction with the same nonce and at least the same priority
+    pub fn is_too_low_priority(&self) -> bool {
+        matches!(
+            self,
+            Self::PoolRejected {
+                code: POOL_TOO_LOW_PRIORITY,
+                ..
+            }
+        )
+    }
+
+    /// Returns the process exit code reported to supervisors when the bot stops on this error
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Other(_) | Self::Runtime(_) => 1,
+            Self::Config(_) => 2,
+            Self::Rpc(_) | Self::Disconnected(_) => 3,
+            Self::InsufficientBalance { .. } => 4,
+            Self::InvalidTransaction(InvalidTransaction::Payment) => 4,
+            Self::Dispatch { error, .. } if error == "NotEnoughBalanceToStake" => 4,
+            Self::InvalidTransaction(_) | Self::PoolRejected { .. } => 5,
+            Self::Dispatch { .. } => 6,
+        }
+    }
+}
+
+impl fmt::Display for RegbotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(message) => write!(f, "Configuration error: {}", message),
+            Self::InsufficientBalance { free, required } => write!(
+                f,
+                "Insufficient balance: {} free, {} required for burn, tip and fee",
+                format_tao(*free),
+                format_tao(*required)
+            ),
+            Self::Rpc(message) => write!(f, "RPC error: {}", message),
+            Self::Disconnected(message) => write!(f, "Disconnected: {}", message),
+            Self::InvalidTransaction(reason) => write!(f, "Invalid transaction: {}", reason),
+            Self::PoolRejected { code, message } => {
+                write!(f, "Transaction rejected by pool ({}): {}", code, message)
+            }
+            Self::Dispatch { pallet, error, .. } => {
+                write!(f, "Dispatch error: {}::{}", pallet, error)
+            }
+            Self::Runtime(message) => write!(f, "Runtime error: {}", message),
+            Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for RegbotError {}
+
+impl From<subxt::Error> for RegbotError {
+    fn from(error: subxt::Error) -> Self {
+        match error {
+            subxt::Error::Rpc(RpcError::ClientError(client_error)) => {
+                match client_error.downcast_ref::<ClientError>() {
+                    Some(ClientError::Call(call)) => {
+                        let data = call
+                            .data()
+                            .map(|raw| {
+                                serde_json::from_str::<String>(raw.get())
+                                    .unwrap_or_else(|_| raw.get().to_string())
+                            })
+                            .unwrap_or_default();
+                        match call.code() {
+                            POOL_INVALID_TX | POOL_UNKNOWN_VALIDITY => {
+                                Self::InvalidTransaction(InvalidTransaction::from_message(&data))
+                            }
+                            code @ (POOL_ALREADY_IMPORTED | POOL_TOO_LOW_PRIORITY) => {
+                                Self::PoolRejected {
+                                    code,
+                                    message: call.message().to_string(),
+                                }
+                            }
+                            code => Self::PoolRejected {
+                                code,
+                                message: format!("{} {}", call.message(), data),
+                            },
+                        }
+                    }
+                    Some(ClientError::RestartNeeded(_)) | Some(ClientError::Transport(_)) => {
+                        Self::Disconnected(client_error.to_string())
+                    }
+                    _ => Self::Rpc(client_error.to_string()),
+                }
+            }
+            subxt::Error::Rpc(e @ RpcError::SubscriptionDropped)
+            | subxt::Error::Rpc(e @ RpcError::DisconnectedWillReconnect(_)) => {
+                Self::Disconnected(e.to_string())
+            }
+            subxt::Error::Rpc(e) => Self::Rpc(e.to_string()),
+            subxt::Error::Runtime(e) => Self::from(e),
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<DispatchError> for RegbotError {
+    fn from(error: DispatchError) -> Self {
+        match error {
+            DispatchError::Module(module_error) => match module_error.details() {
+                Ok(details) => Self::Dispatch {
+                    pallet: details.pallet.name().to_string(),
+                    error: details.variant.name.clone(),
+                    docs: details.variant.docs.join(" ").trim().to_string(),
+                },
+                Err(_) => Self::Runtime(module_error.to_string()),
+            },
+            e => Self::Runtime(e.to_string()),
+        }
+    }
+}
+
+impl From<DecodeError> for RegbotError {
+    fn from(error: DecodeError) -> Self {
+        Self::Other(error.to_string())
+    }
+}
+
+impl From<String> for RegbotError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for RegbotError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatch(error: &str) -> RegbotError {
+        RegbotError::Dispatch {
+            pallet: "SubtensorModule".to_string(),
+            error: error.to_string(),
+            docs: String::new(),
+        }
+    }
+
+    #[test]
+    fn node_messages_map_to_their_reason() {
+        let cases = [
+            ("Transaction is outdated", InvalidTransaction::Stale),
+            (
+                "Transaction will be valid in the future",
+                InvalidTransaction::Future,
+            ),
+            (
+                "Inability to pay some fees (e.g. account balance too low)",
+                InvalidTransaction::Payment,
+            ),
+            (
+                "Transaction would exhaust the block limits",
+                InvalidTransaction::ExhaustsResources,
+            ),
+            (
+                "Transaction has a bad signature",
+                InvalidTransaction::BadProof,
+            ),
+            (
+                "Transaction has an ancient birth block",
+                InvalidTransaction::AncientBirthBlock,
+            ),
+            ("Custom error: 0", InvalidTransaction::Custom(0)),
+            ("Custom error: 255", InvalidTransaction::Custom(255)),
+            (
+                "Custom error: 256",
+                InvalidTransaction::Other("Custom error: 256".to_string()),
+            ),
+            (
+                "Transaction call is not expected",
+                InvalidTransaction::Other("Transaction call is not expected".to_string()),
+            ),
+        ];
+        for (message, reason) in cases {
+            assert_eq!(
+                InvalidTransaction::from_message(message),
+                reason,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn errors_map_to_their_retry_policy() {
+        use RetryPolicy::{Abort, NextSlot};
+        let cases = [
+            (RegbotError::Config(String::new()), Abort),
+            (
+                RegbotError::InsufficientBalance {
+                    free: 0,
+                    required: 1,
+                },
+                Abort,
+            ),
+            (RegbotError::Rpc(String::new()), NextSlot),
+            (RegbotError::Disconnected(String::new()), NextSlot),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Stale),
+                NextSlot,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Future),
+                NextSlot,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::ExhaustsResources),
+                NextSlot,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::AncientBirthBlock),
+                NextSlot,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Custom(1)),
+                NextSlot,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Payment),
+                Abort,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::BadProof),
+                Abort,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Other(String::new())),
+                Abort,
+            ),
+            (
+                RegbotError::PoolRejected {
+                    code: POOL_TOO_LOW_PRIORITY,
+                    message: String::new(),
+                },
+                NextSlot,
+            ),
+            (dispatch("TooManyRegistrationsThisBlock"), NextSlot),
+            (dispatch("TooManyRegistrationsThisInterval"), NextSlot),
+            (dispatch("HotKeyAlreadyRegisteredInSubNet"), NextSlot),
+            (dispatch("NotEnoughBalanceToStake"), Abort),
+            (dispatch("SubNetworkDoesNotExist"), Abort),
+            (RegbotError::Runtime(String::new()), NextSlot),
+            (RegbotError::Other(String::new()), NextSlot),
+        ];
+        for (error, policy) in cases {
+            assert_eq!(error.retry_policy(), policy, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn errors_map_to_their_exit_code() {
+        let cases = [
+            (RegbotError::Other(String::new()), 1),
+            (RegbotError::Runtime(String::new()), 1),
+            (RegbotError::Config(String::new()), 2),
+            (RegbotError::Rpc(String::new()), 3),
+            (RegbotError::Disconnected(String::new()), 3),
+            (
+                RegbotError::InsufficientBalance {
+                    free: 0,
+                    required: 1,
+                },
+                4,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Payment),
+                4,
+            ),
+            (dispatch("NotEnoughBalanceToStake"), 4),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::Stale),
+                5,
+            ),
+            (
+                RegbotError::InvalidTransaction(InvalidTransaction::BadProof),
+                5,
+            ),
+            (
+                RegbotError::PoolRejected {
+                    code: POOL_ALREADY_IMPORTED,
+                    message: String::new(),
+                },
+                5,
+            ),
+            (dispatch("SubNetworkDoesNotExist"), 6),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn pool_rejections_are_told_apart() {
+        let bad_nonce = RegbotError::InvalidTransaction(InvalidTransaction::Stale);
+        assert!(bad_nonce.is_bad_nonce());
+        let imported = RegbotError::PoolRejected {
+            code: POOL_ALREADY_IMPORTED,
+            message: String::new(),
+        };
+        assert!(imported.is_already_imported() && !imported.is_too_low_priority());
+        let too_low = RegbotError::PoolRejected {
+            code: POOL_TOO_LOW_PRIORITY,
+            message: String::new(),
+        };
+        assert!(too_low.is_too_low_priority() && !too_low.is_already_imported());
+    }
+}
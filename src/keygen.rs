@@ -0,0 +1,160 @@
+//! Generation of fresh sr25519 hotkeys, written as btcli keyfiles so the wallet can use them like any other hotkey.
+
+use crate::keys::Hotkey;
+use crate::queue::{HotkeySource, HotkeyStatus};
+use log::{error, info};
+use schnorrkel::{ExpansionMode, MiniSecretKey};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use subxt::ext::sp_core::crypto::Ss58Codec;
+use subxt::ext::sp_core::{bytes, sr25519, Pair};
+use subxt::utils::AccountId32;
+use zeroize::Zeroizing;
+
+/// Prefix of the names given to hotkeys generated by `--auto-hotkey`
+const AUTO_HOTKEY_PREFIX: &str = "regbot-";
+
+/// Contents of an unencrypted btcli keyfile
+#[derive(Serialize)]
+struct Keyfile<'a> {
+    #[serde(rename = "accountId")]
+    account_id: &'a str,
+    #[serde(rename = "publicKey")]
+    public_key: &'a str,
+    #[serde(rename = "privateKey")]
+    private_key: &'a str,
+    #[serde(rename = "secretPhrase")]
+    secret_phrase: &'a str,
+    #[serde(rename = "secretSeed")]
+    secret_seed: &'a str,
+    #[serde(rename = "ss58Address")]
+    ss58_address: &'a str,
+}
+
+/// Generates a hotkey from a new mnemonic and writes it to `<wallet_path>/<wallet_name>/hotkeys/<hotkey_name>`
+///
+/// The keyfile follows the btcli JSON layout and is only readable by its owner. An existing keyfile is never
+/// overwritten.
+///
+/// # Arguments
+///
+/// * `wallet_path` - The directory containing all wallets
+/// * `wallet_name` - The name of the wallet
+/// * `hotkey_name` - The name of the new hotkey within the wallet
+///
+/// # Returns
+///
+/// A `Result` containing the new `Hotkey`, or an `Err` if the keyfile exists or cannot be written
+pub fn generate_hotkey(
+    wallet_path: &Path,
+    wallet_name: &str,
+    hotkey_name: &str,
+) -> Result<Hotkey, Box<dyn std::error::Error>> {
+    let hotkeys_dir = wallet_path.join(wallet_name).join("hotkeys");
+    let path = hotkeys_dir.join(hotkey_name);
+
+    let (pair, phrase, seed) = sr25519::Pair::generate_with_phrase(None);
+    let phrase = Zeroizing::new(phrase);
+    let seed = Zeroizing::new(seed);
+    // btcli stores the secret key in its ed25519-compatible expanded form
+    let private_key = Zeroizing::new(
+        MiniSecretKey::from_bytes(&seed[..])
+            .map_err(|e| format!("Invalid generated seed: {}", e))?
+            .expand(ExpansionMode::Ed25519)
+            .to_ed25519_bytes(),
+    );
+
+    let public_key = bytes::to_hex(&pair.public().0, false);
+    let ss58_address = pair.public().to_ss58check();
+    let private_key = Zeroizing::new(bytes::to_hex(&private_key[..], false));
+    let secret_seed = Zeroizing::new(bytes::to_hex(&seed[..], false));
+    let contents = Zeroizing::new(serde_json::to_string(&Keyfile {
+        account_id: &public_key,
+        public_key: &public_key,
+        private_key: &private_key,
+        secret_phrase: &phrase,
+        secret_seed: &secret_seed,
+        ss58_address: &ss58_address,
+    })?);
+
+    std::fs::create_dir_all(&hotkeys_dir)
+        .map_err(|e| format!("Failed to create {}: {}", hotkeys_dir.display(), e))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Failed to write keyfile {}: {}", path.display(), e))?;
+
+    Ok(Hotkey::from(&pair))
+}
+
+/// Generates hotkeys on demand for `--auto-hotkey`, right before each registration
+pub struct HotkeyGenerator {
+    wallet_path: PathBuf,
+    wallet_name: String,
+    remaining: usize,
+}
+
+impl HotkeyGenerator {
+    /// Creates a generator writing hotkeys to a wallet
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet_path` - The directory containing all wallets
+    /// * `wallet_name` - The name of the wallet receiving the hotkeys
+    /// * `count` - The number of hotkeys to generate
+    pub fn new(wallet_path: PathBuf, wallet_name: String, count: usize) -> Self {
+        Self {
+            wallet_path,
+            wallet_name,
+            remaining: count,
+        }
+    }
+
+    /// Returns the first `regbot-<n>` hotkey name not taken in the wallet
+    fn free_name(&self) -> String {
+        let hotkeys_dir = self.wallet_path.join(&self.wallet_name).join("hotkeys");
+        (1..)
+            .map(|n| format!("{}{}", AUTO_HOTKEY_PREFIX, n))
+            .find(|name| !hotkeys_dir.join(name).exists())
+            .unwrap_or_default()
+    }
+}
+
+impl HotkeySource for HotkeyGenerator {
+    fn next(&mut self) -> Option<Hotkey> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let name = self.free_name();
+        match generate_hotkey(&self.wallet_path, &self.wallet_name, &name) {
+            Ok(hotkey) => {
+                self.remaining -= 1;
+                info!(
+                    "🔑 Generated hotkey {} ({}) in wallet {}",
+                    name, hotkey.account, self.wallet_name
+                );
+                Some(hotkey)
+            }
+            Err(e) => {
+                error!("Failed to generate hotkey: {}", e);
+                None
+            }
+        }
+    }
+
+    fn waiting(&self) -> Vec<&Hotkey> {
+        Vec::new()
+    }
+
+    fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    fn record(&mut self, _hotkey: &AccountId32, _status: HotkeyStatus) {}
+}
@@ -0,0 +1,145 @@
+//! Parsing of hotkeys given as public keys or secret URIs.
+//!
+//! `burned_register` only carries the hotkey's public key, so an SS58 address or a `pub:0x`-prefixed hex public key
+//! is enough and the hotkey secret never has to touch the registration host. Secret URIs are still accepted for
+//! compatibility, including bare `0x` hex seeds.
+
+use crate::error::RegbotError;
+use subxt::ext::sp_core::crypto::Ss58Codec;
+use subxt::ext::sp_core::{bytes, sr25519, Pair};
+use subxt::utils::AccountId32;
+use subxt::{OnlineClient, SubstrateConfig};
+
+/// Prefix marking a hotkey given as a hex public key, which a bare `0x` hex string would be read as a seed
+const PUBLIC_KEY_PREFIX: &str = "pub:";
+
+/// A hotkey to register
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hotkey {
+    /// The account ID (public key) of the hotkey
+    pub account: AccountId32,
+    /// The SS58 prefix of the address, when the hotkey was given as one
+    pub ss58_prefix: Option<u16>,
+}
+
+impl Hotkey {
+    /// Checks that an address given in SS58 form was encoded for the chain we register on
+    ///
+    /// # Arguments
+    ///
+    /// * `chain_prefix` - The SS58 prefix of the chain
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` if the prefix matches or the hotkey was not given as an address, or an `Err` otherwise
+    pub fn check_ss58_prefix(&self, chain_prefix: u16) -> Result<(), RegbotError> {
+        match self.ss58_prefix {
+            Some(prefix) if prefix != chain_prefix => Err(RegbotError::Config(format!(
+                "hotkey {} is an SS58 address with prefix {}, but the chain uses prefix {}",
+                self.account, prefix, chain_prefix
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&sr25519::Pair> for Hotkey {
+    fn from(pair: &sr25519::Pair) -> Self {
+        Self {
+            account: AccountId32::from(pair.public().0),
+            ss58_prefix: None,
+        }
+    }
+}
+
+/// Parses a hotkey given as an SS58 address, a hex public key or a secret URI
+///
+/// A hex public key must be written `pub:0x…`; a bare `0x`-prefixed hex string keeps its meaning as a secret seed.
+///
+/// # Arguments
+///
+/// * `value` - The hotkey as given by the user
+///
+/// # Returns
+///
+/// A `Result` containing the `Hotkey`, or an `Err` if the value is none of the accepted forms
+pub fn parse_hotkey(value: &str) -> Result<Hotkey, String> {
+    if let Ok((public, format)) = sr25519::Public::from_ss58check_with_version(value) {
+        return Ok(Hotkey {
+            account: AccountId32::from(public.0),
+            ss58_prefix: Some(u16::from(format)),
+        });
+    }
+
+    if let Some(hex) = value.strip_prefix(PUBLIC_KEY_PREFIX) {
+        let public: [u8; 32] = hex
+            .strip_prefix("0x")
+            .and_then(|hex| bytes::from_hex(hex).ok())
+            .and_then(|public| public.try_into().ok())
+            .ok_or("Invalid hex public key for hotkey: expected `pub:0x` and 64 hex digits")?;
+        return Ok(Hotkey {
+            account: AccountId32::from(public),
+            ss58_prefix: None,
+        });
+    }
+
+    // Never echo the value back, it may be a secret
+    sr25519::Pair::from_string(value, None)
+        .map(|pair| Hotkey::from(&pair))
+        .map_err(|_| {
+            "Invalid hotkey: not an SS58 address, `pub:0x` hex public key or secret URI".to_string()
+        })
+}
+
+/// Reads the SS58 prefix of the chain from the `System::SS58Prefix` constant
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+///
+/// # Returns
+///
+/// A `Result` containing the prefix, or an `Err` if the constant is missing from the metadata
+pub fn chain_ss58_prefix(client: &OnlineClient<SubstrateConfig>) -> Result<u16, RegbotError> {
+    let prefix = client
+        .constants()
+        .at(&subxt::dynamic::constant("System", "SS58Prefix"))?
+        .as_type::<u16>()?;
+
+    Ok(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+    #[test]
+    fn bare_hex_is_a_seed() {
+        let hotkey = parse_hotkey(HEX).unwrap();
+        let pair = sr25519::Pair::from_string(HEX, None).unwrap();
+        assert_eq!(hotkey.account, AccountId32::from(pair.public().0));
+    }
+
+    #[test]
+    fn prefixed_hex_is_a_public_key() {
+        let hotkey = parse_hotkey(&format!("pub:{}", HEX)).unwrap();
+        assert_eq!(hotkey.account.0.to_vec(), bytes::from_hex(HEX).unwrap());
+        assert_eq!(hotkey.ss58_prefix, None);
+    }
+
+    #[test]
+    fn malformed_public_key_is_rejected() {
+        assert!(parse_hotkey("pub:0x1234").is_err());
+        assert!(parse_hotkey(&format!("pub:{}", &HEX[2..])).is_err());
+    }
+
+    #[test]
+    fn ss58_address_keeps_its_prefix() {
+        let hotkey = parse_hotkey("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
+        assert_eq!(hotkey.ss58_prefix, Some(42));
+        assert!(hotkey.check_ss58_prefix(42).is_ok());
+        assert!(hotkey.check_ss58_prefix(0).is_err());
+    }
+}
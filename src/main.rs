@@ -1,42 +1,48 @@
 //! This module implements a registration script for a blockchain network.
 //! It allows users to register hotkeys using provided coldkeys and other parameters.
 
-use clap::Parser;
+mod balance;
+mod blocks;
+mod config;
+mod connection;
+mod error;
+mod keygen;
+mod keys;
+mod nonce;
+mod queue;
+mod schedule;
+mod secret;
+mod submit;
+mod tip;
+mod wallet;
+mod watch;
+
+use balance::{format_tao, get_account, AccountState};
+use blocks::{BlockTracker, ChainBlock};
+use config::{parse_config, KeygenParams, Mode, RegistrationParams, Subcommands};
+use connection::EndpointPool;
+use error::{RegbotError, RetryPolicy};
+use futures::future::join_all;
+use keygen::{generate_hotkey, HotkeyGenerator};
+use keys::{chain_ss58_prefix, parse_hotkey, Hotkey};
 use log::{error, info, warn};
+use nonce::NonceTracker;
+use queue::{default_state_path, HotkeyQueue, HotkeySource, HotkeyStatus};
 use scale_value::{Composite, Value};
-use serde::Deserialize;
+use schedule::SlotSchedule;
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::ExitCode;
 use std::time::{Duration, Instant};
+use submit::{mortality_period, submit_signed, PresignState, PresignedExtrinsic};
+use subxt::config::Hasher;
+use subxt::error::DispatchError;
+use subxt::events::StaticEvent;
+use subxt::ext::scale_decode::DecodeAsType;
 use subxt::ext::sp_core::{sr25519, Pair};
 use subxt::tx::DefaultPayload;
+use subxt::utils::{AccountId32, H256};
 use subxt::{tx::PairSigner, OnlineClient, SubstrateConfig};
-
-/// Struct to hold registration parameters, can be parsed from command line or config file
-#[derive(Parser, Deserialize, Debug)]
-#[clap(author, version, about, long_about = None)]
-struct RegistrationParams {
-    #[clap(long)]
-    coldkey: String,
-
-    #[clap(long)]
-    hotkey: String,
-
-    #[clap(long)]
-    netuid: u16,
-
-    #[clap(long, default_value = "5000000000")]
-    max_cost: u64,
-
-    #[clap(long, default_value = "wss://entrypoint-finney.opentensor.ai:443")]
-    chain_endpoint: String,
-
-    /// Slot number (0, 1, or 2) to determine which block within the 3-block registration window to target.
-    /// - Slot 0: submits on blocks where block_number % 3 == 0
-    /// - Slot 1: submits on blocks where block_number % 3 == 1  
-    /// - Slot 2: submits on blocks where block_number % 3 == 2
-    /// Run 3 instances with --slot 0, --slot 1, --slot 2 to register 3 miners per epoch.
-    #[clap(long, default_value = "0")]
-    slot: u32,
-}
+use tip::TipPolicy;
 
 /// Returns the current date and time in Eastern Time Zone
 ///
@@ -49,48 +55,299 @@ fn get_formatted_date_now() -> String {
     eastern_time.format("%Y-%m-%d %H:%M:%S %Z%z").to_string()
 }
 
-/// Attempts to register a hotkey on the blockchain
+/// Consecutive block fetch failures after which the connection is re-established
+const MAX_BLOCK_FAILURES: u32 = 5;
+
+/// Interval between health checks of the configured endpoints
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Registrations a subnet accepts per interval, as a multiple of `TargetRegistrationsPerInterval`
+const INTERVAL_REGISTRATIONS_PER_TARGET: u32 = 3;
+
+/// `SubtensorModule::NeuronRegistered(netuid, uid, hotkey)` event emitted on successful registration
+#[derive(DecodeAsType, Debug)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+struct NeuronRegistered(u16, u16, AccountId32);
+
+impl StaticEvent for NeuronRegistered {
+    const PALLET: &'static str = "SubtensorModule";
+    const EVENT: &'static str = "NeuronRegistered";
+}
+
+/// Details of a successful registration, as reported by the `NeuronRegistered` event
+#[derive(Debug, Clone)]
+struct Registration {
+    /// Number of the block the registration extrinsic was included in
+    block_number: u32,
+    /// Hash of the block the registration extrinsic was included in
+    block_hash: H256,
+    /// Index of the registration extrinsic within its block
+    extrinsic_index: u32,
+    /// UID assigned to the hotkey on the subnet
+    uid: u16,
+}
+
+/// Registrations already made on a subnet in the current adjustment interval, against the cap `burned_register`
+/// enforces
+///
+/// `RegistrationsThisBlock` is not part of it: it is reset at the start of every block, so the value read at the
+/// observed block says nothing about the block our extrinsic lands in.
+#[derive(Debug, Clone, Copy)]
+struct RegistrationWindow {
+    /// `RegistrationsThisInterval`
+    this_interval: u16,
+    /// `TargetRegistrationsPerInterval`
+    target_per_interval: u16,
+    /// `LastAdjustmentBlock`, where the current interval started
+    last_adjustment_block: u64,
+    /// `AdjustmentInterval`, after which the interval counter is reset
+    adjustment_interval: u16,
+}
+
+impl RegistrationWindow {
+    /// Returns why a registration included in `target_block` would fail with `TooManyRegistrationsThisInterval`,
+    /// or `None` if there is room left
+    ///
+    /// The interval counter is reset at the start of the first block at least `AdjustmentInterval` blocks after
+    /// `LastAdjustmentBlock`, so a full interval ending before `target_block` does not block the registration.
+    fn full(&self, target_block: u32) -> Option<String> {
+        let elapsed = (target_block as u64).saturating_sub(self.last_adjustment_block);
+        if elapsed >= self.adjustment_interval as u64 {
+            return None;
+        }
+        let max_per_interval = self.target_per_interval as u32 * INTERVAL_REGISTRATIONS_PER_TARGET;
+        if self.this_interval as u32 >= max_per_interval {
+            return Some(format!(
+                "{} of {} registrations this interval, which resets at block {}",
+                self.this_interval,
+                max_per_interval,
+                self.last_adjustment_block + self.adjustment_interval as u64
+            ));
+        }
+        None
+    }
+}
+
+/// Result of running the registration bot
+#[derive(Debug, Clone)]
+enum RegistrationOutcome {
+    /// One of our extrinsics registered the hotkey
+    Registered(Registration),
+    /// The hotkey was found registered on the subnet without a matching event from us
+    AlreadyRegistered { uid: u16 },
+}
+
+/// A submitted registration extrinsic we are waiting to see included
+struct PendingExtrinsic {
+    tx_hash: H256,
+    nonce: u64,
+    /// Tip paid with the extrinsic, which a replacement must outbid
+    tip: u64,
+    submitted_at_block: u32,
+    /// Last block the extrinsic can be included in before its mortal era ends
+    valid_until: u32,
+}
+
+/// A coldkey/hotkey pair being registered, with its share of the slot schedule and its own submission state
+struct Registrant {
+    /// Signer of the coldkey paying for the registration
+    signer: PairSigner<SubstrateConfig, sr25519::Pair>,
+    coldkey_account: AccountId32,
+    hotkey_account: AccountId32,
+    /// The slots assigned to this pair
+    schedule: SlotSchedule,
+    /// Estimated fee of the registration extrinsic, in rao
+    fee_estimate: u128,
+    /// Tip of the next submission, in rao, raised after every slot we submitted on
+    tip: u64,
+    /// Registration extrinsic signed while waiting, so the slot only needs the submission
+    presigned: Option<PresignedExtrinsic>,
+    /// Extrinsics submitted but not yet seen in a block
+    pending: Vec<PendingExtrinsic>,
+    /// Number of our included extrinsics that failed, per error name
+    failure_counts: BTreeMap<String, u64>,
+    /// How the registration ended, once it did
+    outcome: Option<Result<RegistrationOutcome, RegbotError>>,
+}
+
+impl Registrant {
+    /// Returns whether the pair is still waiting to be registered
+    fn is_active(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    /// Records how the registration ended; extrinsics still in flight are no longer tracked
+    fn finish(&mut self, outcome: Result<RegistrationOutcome, RegbotError>) {
+        self.outcome = Some(outcome);
+        self.presigned = None;
+        self.pending.clear();
+    }
+
+    /// Moves the coldkey, its slots and its fee estimate on to another hotkey, starting with a fresh submission state
+    fn reassign(self, hotkey_account: AccountId32, tip: u64) -> Self {
+        Self {
+            signer: self.signer,
+            coldkey_account: self.coldkey_account,
+            hotkey_account,
+            schedule: self.schedule,
+            fee_estimate: self.fee_estimate,
+            tip,
+            presigned: None,
+            pending: Vec::new(),
+            failure_counts: BTreeMap::new(),
+            outcome: None,
+        }
+    }
+}
+
+/// Attempts to register every coldkey/hotkey pair, sharing one client and one block stream between them
 ///
 /// # Arguments
 ///
 /// * `params` - A reference to `RegistrationParams` containing registration details
+/// * `schedule` - The slot schedule, dealt out to the pairs
+/// * `tips` - The tip policy, applied to each pair separately
+/// * `pairs` - The coldkey pairs signing and paying for the registrations, with the hotkeys being registered
+/// * `source` - The hotkeys each coldkey moves on to after a registration, with `--hotkeys-file` or `--auto-hotkey`
 ///
 /// # Returns
 ///
-/// A `Result` which is `Ok` if registration is successful, or an `Err` containing the error message
-// TODO: Parse event and decode Registered event
-async fn register_hotkey(params: &RegistrationParams) -> Result<(), Box<dyn std::error::Error>> {
+/// A `Result` containing each hotkey with its outcome, in order, once none is left waiting, or an `Err` containing
+/// the error message if the bot could not start
+async fn register_hotkeys(
+    params: &RegistrationParams,
+    schedule: &SlotSchedule,
+    tips: &TipPolicy,
+    pairs: Vec<(sr25519::Pair, Hotkey)>,
+    source: &mut Option<Box<dyn HotkeySource>>,
+) -> Result<Vec<(AccountId32, Result<RegistrationOutcome, RegbotError>)>, RegbotError> {
+    let schedules = schedule.split(pairs.len()).map_err(RegbotError::Config)?;
+
     // Initialize client connection to the blockchain
-    let client = OnlineClient::<SubstrateConfig>::from_url(&params.chain_endpoint).await?;
+    let mut endpoints = EndpointPool::connect(&params.chain_endpoint).await?;
+    let mut client = endpoints.client();
 
-    // Parse coldkey and hotkey from provided strings
-    let coldkey: sr25519::Pair =
-        sr25519::Pair::from_string(&params.coldkey, None).map_err(|_| "Invalid coldkey")?;
-    let hotkey: sr25519::Pair =
-        sr25519::Pair::from_string(&params.hotkey, None).map_err(|_| "Invalid hotkey")?;
+    // Catch addresses encoded for another network before anything is signed
+    let chain_prefix = chain_ss58_prefix(&client)?;
+    let queued = source
+        .as_deref()
+        .map(HotkeySource::waiting)
+        .unwrap_or_default();
+    for hotkey in pairs.iter().map(|(_, hotkey)| hotkey).chain(queued) {
+        hotkey.check_ss58_prefix(chain_prefix)?;
+    }
+
+    // Verify at startup so a misconfigured run ends immediately
+    let latest_hash = client.blocks().at_latest().await?.hash();
+    let mut nonces = NonceTracker::new();
+    let mut registrants: Vec<Registrant> = Vec::with_capacity(pairs.len());
+    for (
+        (
+            coldkey,
+            Hotkey {
+                account: hotkey_account,
+                ..
+            },
+        ),
+        schedule,
+    ) in pairs.into_iter().zip(schedules)
+    {
+        let signer = PairSigner::new(coldkey.clone());
+        let coldkey_account = AccountId32::from(coldkey.public().0);
 
-    let signer = PairSigner::new(coldkey.clone());
+        let uid = get_hotkey_uid(&client, params.netuid, &hotkey_account, latest_hash).await?;
+        let outcome = uid.map(|uid| Ok(RegistrationOutcome::AlreadyRegistered { uid }));
+
+        if outcome.is_none() {
+            nonces.next(&endpoints.rpc(), &coldkey_account).await?;
+        }
+        // Estimate the fee once; it only depends on the call and the runtime's fee parameters. Pairs already
+        // registered need it too, since their coldkey moves on to the next hotkey of the source with it
+        let fee_estimate = client
+            .tx()
+            .create_signed(
+                &registration_payload(params.netuid, &hotkey_account),
+                &signer,
+                Default::default(),
+            )
+            .await?
+            .partial_fee_estimate()
+            .await?;
+        info!(
+            "🔑 Hotkey {} (coldkey {}) will submit on {}, estimated fee: {}",
+            hotkey_account,
+            coldkey_account,
+            schedule,
+            format_tao(fee_estimate)
+        );
+
+        registrants.push(Registrant {
+            signer,
+            coldkey_account,
+            hotkey_account,
+            schedule,
+            fee_estimate,
+            tip: tips.initial(),
+            presigned: None,
+            pending: Vec::new(),
+            failure_counts: BTreeMap::new(),
+            outcome,
+        });
+    }
 
     // Track the last block we submitted on to avoid duplicate submissions
     let mut last_submitted_block: u32 = 0;
+    // Blocks searched for our pending extrinsics, from the oldest submission still pending
+    let mut inspected: BTreeSet<(u32, H256)> = BTreeSet::new();
     let mut loop_count: u64 = 0;
 
     info!(
-        "🚀 Starting registration bot for slot {} (will submit on blocks where block_number % 3 == {})",
-        params.slot, params.slot
+        "🚀 Starting registration bot for {} key pair(s) (will submit on {}, {})",
+        registrants.len(),
+        schedule,
+        tips
+    );
+    info!(
+        "⌛ Registration extrinsics stay valid for {} blocks from the block they are signed at",
+        mortality_period(params.mortality_blocks)
     );
 
-    // Main registration loop - poll for latest block continuously
-    // This approach is more reliable than subscriptions for time-sensitive operations
+    // Main registration loop - driven by polling or new-head subscriptions, see `--block-source`
+    let mut block_tracker = BlockTracker::new(client.clone(), params.block_source);
+
+    // Consecutive failures to fetch a block; too many means the connection is unusable even if still open
+    let mut block_failures: u32 = 0;
+    let mut needs_reconnect = false;
+    let mut last_health_check = Instant::now();
+
+    // Hotkeys whose registration ended, with the outcome
+    let mut outcomes: Vec<(AccountId32, Result<RegistrationOutcome, RegbotError>)> = Vec::new();
+
     loop {
-        // Small delay to prevent overwhelming the RPC
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        retire_finished(&mut registrants, source, &mut outcomes, tips);
+        if registrants.is_empty() {
+            return Ok(outcomes);
+        }
+
+        // Slot tracking state (`last_submitted_block`, `pending`) lives outside the client and survives this
+        if needs_reconnect {
+            endpoints.reconnect().await;
+            client = endpoints.client();
+            registrants.iter_mut().for_each(|r| r.presigned = None);
+            block_tracker = BlockTracker::new(client.clone(), params.block_source);
+            block_failures = 0;
+            needs_reconnect = false;
+        }
 
-        // Fetch the absolute latest block
-        let latest_block = match client.blocks().at_latest().await {
-            Ok(b) => b,
+        let latest_block = match block_tracker.next().await {
+            Ok(b) => {
+                block_failures = 0;
+                b
+            }
             Err(e) => {
                 warn!("Failed to fetch latest block: {:?}", e);
+                block_failures += 1;
+                needs_reconnect = e.is_disconnected() || block_failures >= MAX_BLOCK_FAILURES;
                 continue;
             }
         };
@@ -98,104 +355,1164 @@ async fn register_hotkey(params: &RegistrationParams) -> Result<(), Box<dyn std:
         let block_number = latest_block.header().number;
         let block_hash = latest_block.hash();
 
-        // Skip if we already submitted for this block
+        // Skip if we already submitted for this block, still looking for our pending extrinsics in a block
+        // that replaced it in a reorg
         if block_number <= last_submitted_block {
+            track_pending(
+                &client,
+                &latest_block,
+                &mut registrants,
+                &mut inspected,
+                &mut nonces,
+                params.netuid,
+            )
+            .await;
             continue;
         }
 
-        // Check if this block matches our designated slot
-        // Each instance targets blocks where block_number % 3 == slot
-        let block_slot = block_number % 3;
-        if block_slot != params.slot {
-            // Update last seen block but don't submit
-            last_submitted_block = block_number;
+        // Check which of our pairs this block is a slot for
+        let block_slot = schedule.slot_of(block_number);
+        let mut due: Vec<usize> = (0..registrants.len())
+            .filter(|&i| {
+                registrants[i].is_active() && registrants[i].schedule.matches(block_number)
+            })
+            .collect();
+        // Update last seen block, whether we submit or not
+        last_submitted_block = block_number;
+
+        if due.is_empty() {
             info!(
-                "⏭️ Skipping block {} (slot {}), waiting for slot {}",
-                block_number, block_slot, params.slot
+                "⏭️ Skipping block {} (slot {}), waiting for {}",
+                block_number, block_slot, schedule
             );
-            continue;
+        } else {
+            // This is one of our slots! Submit immediately
+            loop_count += 1;
+
+            // Check the burn, the registration caps and every due pair at the exact block we are targeting before
+            // spending anything, in one round trip
+            let (burn_cost, window, pair_states) = tokio::join!(
+                get_recycle_cost(&client, params.netuid, block_hash),
+                get_registration_window(&client, params.netuid, block_hash),
+                join_all(due.iter().map(|&index| get_pair_state(
+                    &client,
+                    params.netuid,
+                    &registrants[index],
+                    block_hash
+                )))
+            );
+            // An unusable slot only skips the submissions; ranking, nonce reconciliation and pre-signing still run
+            match slot_burn_cost(params, block_number, burn_cost, window) {
+                Some(burn_cost) => {
+                    let slot = Slot {
+                        block: &latest_block,
+                        slot: block_slot,
+                        burn_cost,
+                        attempt: loop_count,
+                    };
+                    for (&index, pair_state) in due.iter().zip(pair_states) {
+                        needs_reconnect |= submit_for_pair(
+                            params,
+                            tips,
+                            &endpoints,
+                            &mut nonces,
+                            &mut registrants[index],
+                            &slot,
+                            pair_state,
+                        )
+                        .await;
+                    }
+                }
+                None => due.clear(),
+            }
         }
 
-        // This is our slot! Submit immediately
-        last_submitted_block = block_number;
-        loop_count += 1;
+        // Look for our pending extrinsics in every block since the last one inspected, after the submissions so
+        // a slot never waits for it
+        track_pending(
+            &client,
+            &latest_block,
+            &mut registrants,
+            &mut inspected,
+            &mut nonces,
+            params.netuid,
+        )
+        .await;
 
-        info!(
-            "{} | {} | 🎯 Slot {} - Attempting registration for block {} (hash: {})",
-            loop_count,
-            get_formatted_date_now(),
-            params.slot,
-            block_number,
-            block_hash
-        );
+        // Re-rank endpoints on blocks we do not target, so the probes never delay a submission
+        // With every block targeted there are no idle blocks, so re-rank right after submitting
+        if (due.is_empty() || schedule.is_every_block())
+            && check_endpoints(&mut endpoints, &mut last_health_check).await
+        {
+            client = endpoints.client();
+            block_tracker = BlockTracker::new(client.clone(), params.block_source);
+            registrants.iter_mut().for_each(|r| r.presigned = None);
+        }
 
-        // Prepare transaction payload fresh for each submission
-        let call_data = Composite::named([
-            ("netuid", params.netuid.into()),
-            ("hotkey", hotkey.public().0.to_vec().into()),
-        ]);
+        // Reconcile the local nonces with the chain, off the submission path
+        if !needs_reconnect {
+            reconcile_nonces(&latest_block, &registrants, &mut nonces).await;
+        }
 
-        let payload = DefaultPayload::new("SubtensorModule", "burned_register", call_data);
+        // Prepare the extrinsics for the upcoming slots, rebuilding those that went stale. Pairs that just
+        // submitted are included, as with `--every-block` or back-to-back slots their next slot is the next block
+        for registrant in registrants.iter_mut().filter(|r| r.is_active()) {
+            if needs_reconnect {
+                break;
+            }
+            // Reconnecting fetches the new metadata before the next signature
+            needs_reconnect = presign(params, &client, &latest_block, registrant, &nonces).await;
+        }
 
-        // Sign and submit the transaction
-        // Use sign_and_submit (fire-and-forget) with Default params
-        // Default params automatically fetch the correct finalized block checkpoint
-        let sign_and_submit_start: Instant = Instant::now();
+        // Continue immediately to poll for next block
+    }
+}
 
-        let tx_hash = match client.tx().sign_and_submit_default(&payload, &signer).await {
-            Ok(hash) => hash,
-            Err(e) => {
-                let error_str = format!("{:?}", e);
-                // Check for recoverable errors
-                if error_str.contains("TooManyConsumers")
-                    || error_str.contains("InvalidTransaction")
-                    || error_str.contains("Stale")
-                    || error_str.contains("nonce")
-                    || error_str.contains("outdated")
-                    || error_str.contains("Transaction is outdated")
-                {
-                    warn!(
-                        "Recoverable error detected, will retry on next matching slot: {:?}",
-                        e
-                    );
-                } else {
-                    error!("Transaction submission failed: {:?}", e);
+/// Sets finished pairs aside with their outcome, moving their coldkey on to the next hotkey after a registration
+///
+/// # Arguments
+///
+/// * `registrants` - The pairs being registered, from which the finished ones are removed or reassigned
+/// * `source` - The hotkeys each coldkey moves on to, told about every outcome
+/// * `outcomes` - The hotkeys whose registration ended, with the outcome
+/// * `tips` - The tip policy, giving the first tip of a reassigned coldkey
+fn retire_finished(
+    registrants: &mut Vec<Registrant>,
+    source: &mut Option<Box<dyn HotkeySource>>,
+    outcomes: &mut Vec<(AccountId32, Result<RegistrationOutcome, RegbotError>)>,
+    tips: &TipPolicy,
+) {
+    let mut index = 0;
+    while index < registrants.len() {
+        let Some(outcome) = registrants[index].outcome.take() else {
+            index += 1;
+            continue;
+        };
+        let finished = registrants.remove(index);
+        let mut next = None;
+        if let Some(source) = source.as_deref_mut() {
+            let status = match &outcome {
+                Ok(RegistrationOutcome::Registered(Registration { uid, .. }))
+                | Ok(RegistrationOutcome::AlreadyRegistered { uid }) => {
+                    HotkeyStatus::Registered { uid: *uid }
                 }
-                continue;
+                Err(e) => HotkeyStatus::Failed {
+                    error: e.to_string(),
+                },
+            };
+            source.record(&finished.hotkey_account, status);
+            // An error is likely to hit the next hotkey of the same coldkey too, so that coldkey stops
+            if outcome.is_ok() {
+                next = source.next();
             }
+        }
+        outcomes.push((finished.hotkey_account.clone(), outcome));
+        if let Some(Hotkey {
+            account: hotkey_account,
+            ..
+        }) = next
+        {
+            info!(
+                "➡️ Coldkey {} moves on to hotkey {} ({} more queued)",
+                finished.coldkey_account,
+                hotkey_account,
+                source.as_deref().map_or(0, HotkeySource::remaining)
+            );
+            registrants.insert(index, finished.reassign(hotkey_account, tips.initial()));
+            index += 1;
+        }
+    }
+}
+
+/// Reconciles the local nonce of every active coldkey with its account nonce at a block
+///
+/// # Arguments
+///
+/// * `block` - The block whose state holds the account nonces
+/// * `registrants` - The pairs being registered
+/// * `nonces` - The nonce tracker
+async fn reconcile_nonces(
+    block: &ChainBlock,
+    registrants: &[Registrant],
+    nonces: &mut NonceTracker,
+) {
+    let coldkeys: BTreeSet<&AccountId32> = registrants
+        .iter()
+        .filter(|r| r.is_active())
+        .map(|r| &r.coldkey_account)
+        .collect();
+    for coldkey in coldkeys {
+        match block.account_nonce(coldkey).await {
+            Ok(chain_nonce) => nonces.reconcile(coldkey, chain_nonce),
+            Err(e) => warn!("Failed to read the nonce of coldkey {}: {}", coldkey, e),
+        }
+    }
+}
+
+/// Prepares the extrinsic for the next slot of a pair, rebuilding it if it went stale
+///
+/// A pending extrinsic is replaced with its own nonce; a coldkey whose nonce was rejected is read from the node
+/// again right before its slot, so nothing is pre-signed for it.
+///
+/// # Arguments
+///
+/// * `params` - A reference to `RegistrationParams` containing registration details
+/// * `client` - A reference to the blockchain client
+/// * `block` - The latest block, which the extrinsic is signed at
+/// * `registrant` - The pair to pre-sign for
+/// * `nonces` - The nonce tracker
+///
+/// # Returns
+///
+/// `true` if the runtime was upgraded, so the connection must be re-established to fetch the new metadata
+async fn presign(
+    params: &RegistrationParams,
+    client: &OnlineClient<SubstrateConfig>,
+    block: &ChainBlock,
+    registrant: &mut Registrant,
+    nonces: &NonceTracker,
+) -> bool {
+    let next_nonce = match registrant.pending.last() {
+        Some(tx) => Some(tx.nonce),
+        None => nonces.peek(&registrant.coldkey_account),
+    };
+    let Some(nonce) = next_nonce else {
+        registrant.presigned = None;
+        return false;
+    };
+    if let Some(current) = &registrant.presigned {
+        match current.check(client, block, nonce).await {
+            Ok(PresignState::Current) => return false,
+            Ok(state) => {
+                info!("✍️ Pre-signed extrinsic is stale ({:?}), rebuilding", state);
+                registrant.presigned = None;
+                if state == PresignState::RuntimeUpgraded {
+                    return true;
+                }
+            }
+            Err(e) => {
+                warn!("Failed to check pre-signed extrinsic: {}", e);
+                return false;
+            }
+        }
+    }
+
+    let payload = registration_payload(params.netuid, &registrant.hotkey_account);
+    match PresignedExtrinsic::build(
+        client,
+        &payload,
+        &registrant.signer,
+        nonce,
+        block,
+        params.mortality_blocks,
+        registrant.tip,
+    )
+    .await
+    {
+        Ok(built) => {
+            info!(
+                "✍️ Pre-signed registration extrinsic for hotkey {} (nonce {}, checkpoint block {})",
+                registrant.hotkey_account,
+                built.nonce(),
+                built.checkpoint_block()
+            );
+            registrant.presigned = Some(built);
+        }
+        Err(e) => warn!("Failed to pre-sign registration extrinsic: {}", e),
+    }
+    false
+}
+
+/// The slot being submitted on, shared by every pair due on it
+struct Slot<'a> {
+    /// The observed block, which the extrinsics are signed at and target the block after
+    block: &'a ChainBlock,
+    /// The slot of the observed block
+    slot: u32,
+    /// The burn cost read at the observed block, in rao
+    burn_cost: u64,
+    /// Number of slots submitted on so far, for the logs
+    attempt: u64,
+}
+
+/// Submits the registration of one pair on its slot
+///
+/// Cuts the tip to the budget, replaces or resubmits an extrinsic still pending, checks the coldkey can pay, then
+/// signs and submits. The submission is recorded as pending and the tip raised for the next slot; a pair that
+/// cannot go on is finished.
+///
+/// # Arguments
+///
+/// * `params` - A reference to `RegistrationParams` containing registration details
+/// * `tips` - The tip policy
+/// * `endpoints` - The endpoint pool, whose active client signs and submits
+/// * `nonces` - The nonce tracker shared by every coldkey
+/// * `registrant` - The pair due on this slot
+/// * `slot` - The slot being submitted on
+/// * `pair_state` - The hotkey's UID and coldkey account read at the observed block
+///
+/// # Returns
+///
+/// `true` if the connection to the node was lost and must be re-established
+async fn submit_for_pair(
+    params: &RegistrationParams,
+    tips: &TipPolicy,
+    endpoints: &EndpointPool,
+    nonces: &mut NonceTracker,
+    registrant: &mut Registrant,
+    slot: &Slot<'_>,
+    pair_state: Result<(Option<u16>, AccountState), RegbotError>,
+) -> bool {
+    let block_number = slot.block.header().number;
+
+    let (uid, account) = match pair_state {
+        Ok(pair_state) => pair_state,
+        Err(e) => {
+            warn!(
+                "Failed to check registration status for block {}, skipping slot: {:?}",
+                block_number, e
+            );
+            return false;
+        }
+    };
+    // Stop once the hotkey shows up on the subnet, even if we missed our own event; with an extrinsic still
+    // pending, the blocks are searched for its event first
+    if let Some(uid) = uid {
+        if registrant.pending.is_empty() {
+            registrant.finish(Ok(RegistrationOutcome::AlreadyRegistered { uid }));
+        }
+        return false;
+    }
+
+    let Some(tip) = budgeted_tip(params, registrant, slot.burn_cost, block_number) else {
+        return false;
+    };
+
+    // At most one registration per hotkey is in flight: one still pending is replaced by an extrinsic with the
+    // same nonce, which the pool only accepts with a higher tip. Without a higher tip the pending one missed its
+    // slot and was likely dropped from the pool, so it is resubmitted with the same nonce; the pool refuses that
+    // while it still holds the pending one. An extrinsic whose nonce the chain already moved past was included
+    // and is left to the block search
+    let replaced = registrant
+        .pending
+        .iter()
+        .rev()
+        .find(|tx| tx.nonce >= account.nonce)
+        .map(|tx| (tx.tx_hash, tx.nonce, tx.tip));
+    let resubmitted = matches!(replaced, Some((_, _, pending_tip)) if tip <= pending_tip);
+
+    // Make sure the coldkey can pay the burn, the tip and the fee, or the extrinsic fails and still costs the fee
+    let required = slot.burn_cost as u128 + registrant.fee_estimate + tip as u128;
+    if account.free < required {
+        let e = RegbotError::InsufficientBalance {
+            free: account.free,
+            required,
         };
+        if params.wait_for_funds {
+            warn!(
+                "⏳ {}, coldkey {} waiting for funds before block {}",
+                e, registrant.coldkey_account, block_number
+            );
+        } else {
+            error!(
+                "Registration of hotkey {} stopped: {}",
+                registrant.hotkey_account, e
+            );
+            registrant.finish(Err(e));
+        }
+        return false;
+    }
+
+    info!(
+        "{} | {} | 🎯 Slot {} - Attempting registration of hotkey {} for block {} (hash: {}, burn: {}, tip: {})",
+        slot.attempt,
+        get_formatted_date_now(),
+        slot.slot,
+        registrant.hotkey_account,
+        block_number,
+        slot.block.hash(),
+        format_tao(slot.burn_cost as u128),
+        format_tao(tip as u128)
+    );
+
+    let sign_and_submit_start: Instant = Instant::now();
+    let replaced_nonce = replaced.map(|(_, nonce, _)| nonce);
+    let submission = sign_and_submit(
+        params,
+        endpoints,
+        nonces,
+        registrant,
+        replaced_nonce,
+        slot,
+        tip,
+    )
+    .await;
+    let (tx_hash, nonce, valid_until) = match submission {
+        Ok(submitted) => submitted,
+        Err(e) => {
+            if let Some((pending_hash, _, _)) =
+                replaced.filter(|_| resubmitted && e.is_too_low_priority())
+            {
+                info!(
+                    "⏳ Extrinsic {} of hotkey {} is still in the pool and its tip cannot be raised; waiting for it",
+                    pending_hash,
+                    registrant.hotkey_account
+                );
+                return false;
+            }
+            if e.is_bad_nonce() {
+                // Read the nonce from the node again rather than trusting the local count; a stale replacement
+                // means the extrinsic it replaced was included, which the block search picks up
+                nonces.forget(&registrant.coldkey_account);
+            }
+            let disconnected = e.is_disconnected();
+            match e.retry_policy() {
+                RetryPolicy::NextSlot => warn!(
+                    "Recoverable error detected, will retry on next matching slot: {}",
+                    e
+                ),
+                RetryPolicy::Abort => {
+                    error!("Transaction submission failed: {}", e);
+                    registrant.finish(Err(e));
+                }
+            }
+            return disconnected;
+        }
+    };
+
+    let sign_and_submit_duration = sign_and_submit_start.elapsed();
+    info!(
+        "⏱️ sign_and_submit took {:?}, tx_hash: {}",
+        sign_and_submit_duration, tx_hash
+    );
+    info!(
+        "🎯 [Block {}] Transaction submitted successfully! Hash: {}",
+        block_number, tx_hash
+    );
+    info!("✅ Submission completed! Waiting for inclusion while watching next slots...");
+
+    if let Some((pending_hash, _, pending_tip)) = replaced {
+        if resubmitted {
+            info!(
+                "🔄 Extrinsic {} left the pool unincluded, resubmitted as {} (nonce {}, tip {})",
+                pending_hash,
+                tx_hash,
+                nonce,
+                format_tao(tip as u128)
+            );
+        } else {
+            info!(
+                "🔁 Extrinsic {} usurped by {} (nonce {}, tip raised from {} to {})",
+                pending_hash,
+                tx_hash,
+                nonce,
+                format_tao(pending_tip as u128),
+                format_tao(tip as u128)
+            );
+        }
+        registrant.pending.retain(|tx| tx.tx_hash != pending_hash);
+    }
+    nonces.submitted(&registrant.coldkey_account, nonce);
+    registrant.pending.push(PendingExtrinsic {
+        tx_hash,
+        nonce,
+        tip,
+        submitted_at_block: block_number,
+        valid_until,
+    });
 
-        let sign_and_submit_duration = sign_and_submit_start.elapsed();
+    // Should this slot be missed, the next one is tried with a higher tip
+    let next_tip = tips.escalate(registrant.tip);
+    if next_tip > registrant.tip {
         info!(
-            "⏱️ sign_and_submit took {:?}, tx_hash: {}",
-            sign_and_submit_duration, tx_hash
+            "📈 Tip for the next slot of hotkey {} raised to {}",
+            registrant.hotkey_account,
+            format_tao(next_tip as u128)
         );
+        registrant.tip = next_tip;
+    }
+
+    false
+}
+
+/// Cuts the tip of a pair so burn + tip + fee stays within `--max-cost`
+///
+/// # Arguments
+///
+/// * `params` - A reference to `RegistrationParams` containing the max cost
+/// * `registrant` - The pair due on the slot
+/// * `burn_cost` - The burn cost read at the observed block, in rao
+/// * `block_number` - The observed block, for the logs
+///
+/// # Returns
+///
+/// The tip to pay, or `None` if the burn and the fee alone exceed the max cost
+fn budgeted_tip(
+    params: &RegistrationParams,
+    registrant: &Registrant,
+    burn_cost: u64,
+    block_number: u32,
+) -> Option<u64> {
+    let base_cost = burn_cost as u128 + registrant.fee_estimate;
+    let Some(room) = (params.max_cost as u128).checked_sub(base_cost) else {
+        warn!(
+            "💸 Skipping block {} for hotkey {}: burn cost {} plus fee {} exceeds max cost {}",
+            block_number,
+            registrant.hotkey_account,
+            format_tao(burn_cost as u128),
+            format_tao(registrant.fee_estimate),
+            format_tao(params.max_cost as u128)
+        );
+        return None;
+    };
+    let tip = (registrant.tip as u128).min(room) as u64;
+    if tip < registrant.tip {
         info!(
-            "🎯 [Block {}] Transaction submitted successfully! Hash: {}",
-            block_number, tx_hash
+            "💸 Tip of hotkey {} cut from {} to {} to stay within max cost {}",
+            registrant.hotkey_account,
+            format_tao(registrant.tip as u128),
+            format_tao(tip as u128),
+            format_tao(params.max_cost as u128)
         );
-        info!("✅ Submission completed! Bot continues for next epoch opportunities...");
+    }
+    Some(tip)
+}
 
-        // Continue immediately to poll for next block
+/// Signs the registration of a pair and submits it, using the pre-signed extrinsic when it matches
+///
+/// Fire-and-forget by default, watched in the background with `--watch-submissions`, and pushed to every endpoint
+/// with `--broadcast`.
+///
+/// # Arguments
+///
+/// * `params` - A reference to `RegistrationParams` containing registration details
+/// * `endpoints` - The endpoint pool, whose active client signs and submits
+/// * `nonces` - The nonce tracker shared by every coldkey
+/// * `registrant` - The pair due on the slot
+/// * `nonce` - The nonce of the pending extrinsic being replaced, or `None` for the coldkey's next nonce
+/// * `slot` - The slot being submitted on
+/// * `tip` - The tip to pay, in rao
+///
+/// # Returns
+///
+/// A `Result` containing the hash, nonce and last valid block of the submitted extrinsic, or an `Err` if it could
+/// not be signed or every endpoint rejected it
+async fn sign_and_submit(
+    params: &RegistrationParams,
+    endpoints: &EndpointPool,
+    nonces: &mut NonceTracker,
+    registrant: &mut Registrant,
+    nonce: Option<u64>,
+    slot: &Slot<'_>,
+    tip: u64,
+) -> Result<(H256, u64, u32), RegbotError> {
+    // Pairs sharing a coldkey take their nonces from the same tracker, so none is used twice
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => {
+            nonces
+                .next(&endpoints.rpc(), &registrant.coldkey_account)
+                .await?
+        }
+    };
+    let client = endpoints.client();
+    let prepared = match registrant.presigned.take() {
+        Some(ready) if ready.nonce() == nonce && ready.tip() == tip => ready,
+        _ => {
+            let payload = registration_payload(params.netuid, &registrant.hotkey_account);
+            PresignedExtrinsic::build(
+                &client,
+                &payload,
+                &registrant.signer,
+                nonce,
+                slot.block,
+                params.mortality_blocks,
+                tip,
+            )
+            .await?
+        }
+    };
+
+    let valid_until = prepared.valid_until();
+    let tx_hash = submit_signed(
+        &client,
+        endpoints,
+        prepared.into_signed(),
+        params.watch_submissions,
+        params.broadcast,
+        slot.block.header().number,
+    )
+    .await?;
+    Ok((tx_hash, nonce, valid_until))
+}
+
+/// Re-ranks the endpoints once the health check interval has elapsed
+///
+/// # Arguments
+///
+/// * `endpoints` - The endpoint pool
+/// * `last_health_check` - When the endpoints were last checked, reset when a check runs
+///
+/// # Returns
+///
+/// `true` if the active endpoint changed, so the client and block source must be refreshed
+async fn check_endpoints(endpoints: &mut EndpointPool, last_health_check: &mut Instant) -> bool {
+    if endpoints.len() < 2 || last_health_check.elapsed() < HEALTH_CHECK_INTERVAL {
+        return false;
     }
+    *last_health_check = Instant::now();
+    endpoints.check_health().await
 }
 
-/// Retrieves the current recycle cost for a given network UID
+/// Decides whether a slot can be used, from the burn cost and registration window read at the observed block
+///
+/// # Arguments
+///
+/// * `params` - A reference to `RegistrationParams` containing the max cost and netuid
+/// * `block_number` - The observed block; the extrinsic targets the block after it
+/// * `burn_cost` - The burn cost read at the observed block
+/// * `window` - The registration window read at the observed block
+///
+/// # Returns
+///
+/// `Some(burn_cost)` if the slot can be submitted on, or `None` once the reason to skip it is logged
+fn slot_burn_cost(
+    params: &RegistrationParams,
+    block_number: u32,
+    burn_cost: Result<u64, RegbotError>,
+    window: Result<RegistrationWindow, RegbotError>,
+) -> Option<u64> {
+    let burn_cost = match burn_cost {
+        Ok(cost) => cost,
+        Err(e) => {
+            warn!(
+                "Failed to fetch burn cost for block {}, skipping slot: {:?}",
+                block_number, e
+            );
+            return None;
+        }
+    };
+    if burn_cost > params.max_cost {
+        warn!(
+            "💸 Skipping block {}: burn cost {} exceeds max cost {}",
+            block_number,
+            format_tao(burn_cost as u128),
+            format_tao(params.max_cost as u128)
+        );
+        return None;
+    }
+    match window.map(|window| window.full(block_number + 1)) {
+        Ok(Some(reason)) => {
+            warn!(
+                "🚧 Skipping block {}: registration window full on netuid {} ({})",
+                block_number, params.netuid, reason
+            );
+            return None;
+        }
+        Ok(None) => {}
+        // Only a failed read; submitting still has a chance where skipping has none
+        Err(e) => warn!(
+            "Failed to fetch registration counts for block {}, submitting anyway: {:?}",
+            block_number, e
+        ),
+    }
+    Some(burn_cost)
+}
+
+/// Builds the `burned_register` call for a hotkey
+///
+/// # Arguments
+///
+/// * `netuid` - The network UID to register on
+/// * `hotkey` - The account ID of the hotkey being registered
+///
+/// # Returns
+///
+/// The dynamic `SubtensorModule::burned_register` payload
+fn registration_payload(netuid: u16, hotkey: &AccountId32) -> DefaultPayload<Composite<()>> {
+    let call_data = Composite::named([
+        ("netuid", netuid.into()),
+        ("hotkey", hotkey.0.to_vec().into()),
+    ]);
+
+    DefaultPayload::new("SubtensorModule", "burned_register", call_data)
+}
+
+/// Looks up the UID of a hotkey on a subnet
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `netuid` - The network UID to check
+/// * `hotkey` - The account ID of the hotkey
+/// * `block_hash` - The hash of the block whose storage should be read
+///
+/// # Returns
+///
+/// A `Result` containing `Some(uid)` if the hotkey is registered on the subnet, `None` if not, or an `Err` if retrieval fails
+async fn get_hotkey_uid(
+    client: &OnlineClient<SubstrateConfig>,
+    netuid: u16,
+    hotkey: &AccountId32,
+    block_hash: H256,
+) -> Result<Option<u16>, RegbotError> {
+    let uids_key = subxt::storage::dynamic(
+        "SubtensorModule",
+        "Uids",
+        vec![Value::u128(netuid as u128), Value::from_bytes(hotkey.0)],
+    );
+    let uid = client
+        .storage()
+        .at(block_hash)
+        .fetch(&uids_key)
+        .await?
+        .map(|value| value.as_type::<u16>())
+        .transpose()?;
+
+    Ok(uid)
+}
+
+/// Looks up the UID of a pair's hotkey and the state of its coldkey account, concurrently
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `netuid` - The network UID to check
+/// * `registrant` - The pair to look up
+/// * `block_hash` - The hash of the block whose storage should be read
+///
+/// # Returns
+///
+/// A `Result` containing the hotkey's UID if registered and the coldkey's `AccountState`, or an `Err` if either
+/// lookup fails
+async fn get_pair_state(
+    client: &OnlineClient<SubstrateConfig>,
+    netuid: u16,
+    registrant: &Registrant,
+    block_hash: H256,
+) -> Result<(Option<u16>, AccountState), RegbotError> {
+    tokio::try_join!(
+        get_hotkey_uid(client, netuid, &registrant.hotkey_account, block_hash),
+        get_account(client, &registrant.coldkey_account, block_hash)
+    )
+}
+
+/// Builds the coldkey and hotkey pairs from secret URIs or, when those are absent, from the btcli wallets
+///
+/// Coldkeys and hotkeys are matched in order; a side given once is shared by every pair, so one coldkey can
+/// pay for several hotkeys. With a hotkey source, each coldkey instead takes the next hotkey of the source.
+/// The secret URIs are taken out of `params` and zeroized once the pairs are derived.
+///
+/// # Arguments
+///
+/// * `params` - A mutable reference to `RegistrationParams` containing the key sources
+/// * `source` - The hotkey source, with `--hotkeys-file` or `--auto-hotkey`
+///
+/// # Returns
+///
+/// A `Result` containing the coldkey pairs with their hotkeys, or an `Err` if a key is missing or invalid
+fn load_keypairs(
+    params: &mut RegistrationParams,
+    source: &mut Option<Box<dyn HotkeySource>>,
+) -> Result<Vec<(sr25519::Pair, Hotkey)>, Box<dyn std::error::Error>> {
+    let wallet_path = wallet::expand_home(&params.wallet_path);
+
+    let coldkeys = if !params.coldkey.is_empty() {
+        std::mem::take(&mut params.coldkey)
+            .iter()
+            .map(|uri| {
+                sr25519::Pair::from_string(uri.expose_secret(), None).map_err(|_| "Invalid coldkey")
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else if !params.wallet_name.is_empty() {
+        params
+            .wallet_name
+            .iter()
+            .map(|name| wallet::load_coldkey(&wallet_path, name))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        return Err("Either --coldkey or --wallet-name is required".into());
+    };
+
+    // Each coldkey works through the source on its own, starting with one hotkey
+    if let Some(source) = source.as_deref_mut() {
+        return Ok(coldkeys
+            .into_iter()
+            .map_while(|coldkey| Some((coldkey, source.next()?)))
+            .collect());
+    }
+
+    let hotkeys = if !params.hotkey.is_empty() {
+        std::mem::take(&mut params.hotkey)
+            .iter()
+            .map(|value| parse_hotkey(value.expose_secret()))
+            .collect::<Result<Vec<_>, _>>()?
+    } else if !params.wallet_name.is_empty() {
+        match_up(
+            &params.wallet_name,
+            &params.wallet_hotkey,
+            "--wallet-name",
+            "--wallet-hotkey",
+        )?
+        .into_iter()
+        .map(|(name, hotkey)| {
+            wallet::load_hotkey(&wallet_path, name, hotkey).map(|pair| Hotkey::from(&pair))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+    } else {
+        return Err("Either --hotkey, --hotkeys-file or --wallet-name is required".into());
+    };
+
+    let pairs: Vec<(sr25519::Pair, Hotkey)> = match_up(&coldkeys, &hotkeys, "coldkeys", "hotkeys")?
+        .into_iter()
+        .map(|(coldkey, hotkey)| (coldkey.clone(), hotkey.clone()))
+        .collect();
+
+    let mut distinct: Vec<&AccountId32> = pairs.iter().map(|(_, hotkey)| &hotkey.account).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() != pairs.len() {
+        return Err("The same hotkey is given more than once".into());
+    }
+
+    Ok(pairs)
+}
+
+/// Matches two lists up in order, sharing the only entry of a list given once with every entry of the other
+///
+/// # Arguments
+///
+/// * `left` - The first list
+/// * `right` - The second list
+/// * `left_name` - Name of the first list, for error messages
+/// * `right_name` - Name of the second list, for error messages
+///
+/// # Returns
+///
+/// A `Result` containing the matched entries, or an `Err` if the lengths differ and neither list has a single entry
+fn match_up<'a, L, R>(
+    left: &'a [L],
+    right: &'a [R],
+    left_name: &str,
+    right_name: &str,
+) -> Result<Vec<(&'a L, &'a R)>, String> {
+    let count = left.len().max(right.len());
+    if (left.len() != count && left.len() != 1) || (right.len() != count && right.len() != 1) {
+        return Err(format!(
+            "Got {} {} and {} {}; give the same number of each, or a single one to share",
+            left.len(),
+            left_name,
+            right.len(),
+            right_name
+        ));
+    }
+
+    Ok((0..count)
+        .map(|i| (&left[i % left.len()], &right[i % right.len()]))
+        .collect())
+}
+
+/// Follows the pending extrinsics of every pair up to a new block
+///
+/// Every block between the last one inspected and `head` is searched, covering blocks the block source skipped or
+/// failed to fetch and the new branch after a reorg. Once no block is left out, extrinsics whose mortal era ended
+/// are given up.
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `head` - The newest block
+/// * `registrants` - The pairs being registered
+/// * `inspected` - The blocks already searched, by number and hash, pruned below the oldest pending extrinsic
+/// * `nonces` - The nonce tracker, told about the extrinsics given up
+/// * `netuid` - The network UID we are registering on
+async fn track_pending(
+    client: &OnlineClient<SubstrateConfig>,
+    head: &ChainBlock,
+    registrants: &mut [Registrant],
+    inspected: &mut BTreeSet<(u32, H256)>,
+    nonces: &mut NonceTracker,
+    netuid: u16,
+) {
+    let head_number = head.header().number;
+    let floor = registrants
+        .iter()
+        .filter(|r| r.is_active())
+        .flat_map(|r| &r.pending)
+        .map(|tx| tx.submitted_at_block)
+        .min();
+    let Some(floor) = floor else {
+        inspected.clear();
+        return;
+    };
+    if inspected.contains(&(head_number, head.hash())) {
+        return;
+    }
+    inspected.retain(|&(number, _)| number > floor);
+
+    let ancestors = match uninspected_ancestors(client, head, inspected, floor).await {
+        Ok(ancestors) => ancestors,
+        Err(e) => {
+            warn!(
+                "Failed to fetch the blocks since the last one inspected: {:?}",
+                e
+            );
+            return;
+        }
+    };
+    for block in ancestors.iter().chain([head]) {
+        // A block left out is searched again with the next head
+        if !inspect_block(client, block, registrants, netuid).await {
+            return;
+        }
+        inspected.insert((block.header().number, block.hash()));
+    }
+
+    // Tracked until its mortal era ends, so a hotkey never has two extrinsics in flight
+    for registrant in registrants.iter_mut().filter(|r| r.is_active()) {
+        registrant.pending.retain(|tx| {
+            let expired = head_number > tx.valid_until;
+            if expired {
+                warn!(
+                    "⌛ Extrinsic {} expired unincluded after its mortal era ended at block {}",
+                    tx.tx_hash, tx.valid_until
+                );
+                nonces.dropped(&registrant.coldkey_account, tx.nonce);
+            }
+            !expired
+        });
+    }
+}
+
+/// Fetches the blocks between the last one inspected and `head`, oldest first
+///
+/// Walking back the parent hashes finds blocks the block source never yielded and the blocks of a new branch after
+/// a reorg, which the block numbers alone would hide.
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `head` - The newest block, which is not part of the result
+/// * `inspected` - The blocks already searched, by number and hash
+/// * `floor` - The block our oldest pending extrinsic was submitted on; none can be included at or below it
+///
+/// # Returns
+///
+/// A `Result` containing the blocks not inspected yet below `head`, or an `Err` if one could not be fetched
+async fn uninspected_ancestors(
+    client: &OnlineClient<SubstrateConfig>,
+    head: &ChainBlock,
+    inspected: &BTreeSet<(u32, H256)>,
+    floor: u32,
+) -> Result<Vec<ChainBlock>, RegbotError> {
+    let mut ancestors = Vec::new();
+    let mut number = head.header().number;
+    let mut parent_hash = head.header().parent_hash;
+    while number > floor + 1 && !inspected.contains(&(number - 1, parent_hash)) {
+        let block = client.blocks().at(parent_hash).await?;
+        number = block.header().number;
+        parent_hash = block.header().parent_hash;
+        ancestors.push(block);
+    }
+    ancestors.reverse();
+    Ok(ancestors)
+}
+
+/// Searches one block for the pending extrinsics of every pair, finishing the pairs it registered or failed for good
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `block` - The block to inspect
+/// * `registrants` - The pairs being registered
+/// * `netuid` - The network UID we are registering on
+///
+/// # Returns
+///
+/// `true` if the block was searched for every pair, `false` if its extrinsics or events could not be fetched
+async fn inspect_block(
+    client: &OnlineClient<SubstrateConfig>,
+    block: &ChainBlock,
+    registrants: &mut [Registrant],
+    netuid: u16,
+) -> bool {
+    let mut complete = true;
+    for registrant in registrants
+        .iter_mut()
+        .filter(|r| r.is_active() && !r.pending.is_empty())
+    {
+        match find_registration(
+            client,
+            block,
+            &mut registrant.pending,
+            netuid,
+            &registrant.hotkey_account,
+            &mut registrant.failure_counts,
+        )
+        .await
+        {
+            Ok(Some(registration)) => {
+                registrant.finish(Ok(RegistrationOutcome::Registered(registration)))
+            }
+            Ok(None) => {}
+            Err(e) if e.retry_policy() == RetryPolicy::Abort => {
+                error!(
+                    "Registration of hotkey {} failed on chain: {}",
+                    registrant.hotkey_account, e
+                );
+                registrant.finish(Err(e));
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to inspect block {} for pending extrinsics: {:?}",
+                    block.header().number,
+                    e
+                );
+                complete = false;
+            }
+        }
+    }
+    complete
+}
+
+/// Hashes an extrinsic of a block body the way its hash is reported on submission
+///
+/// Block bodies yield extrinsics without their compact length prefix, while the submission hash covers the
+/// length-prefixed encoding; hashing the bytes as a SCALE-encoded slice puts the prefix back on, as subxt's
+/// `TxProgress` does.
+///
+/// # Arguments
+///
+/// * `bytes` - The extrinsic bytes, without the length prefix
+///
+/// # Returns
+///
+/// The hash returned by `author_submitExtrinsic` for the same extrinsic
+fn extrinsic_hash(bytes: &[u8]) -> H256 {
+    <SubstrateConfig as subxt::Config>::Hasher::hash_of(&bytes)
+}
+
+/// Searches a block for our pending extrinsics and decodes their `NeuronRegistered` event
+///
+/// Pending extrinsics found in the block are removed from `pending`, whether they succeeded or not.
+/// Failed extrinsics have their `ExtrinsicFailed` dispatch error decoded, logged and counted.
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client, whose metadata decodes dispatch errors
+/// * `block` - The block to inspect
+/// * `pending` - The extrinsics submitted but not yet seen in a block
+/// * `netuid` - The network UID we are registering on
+/// * `hotkey` - The account ID of the hotkey being registered
+/// * `failure_counts` - Number of failed extrinsics per error name, updated for failures in this block
+///
+/// # Returns
+///
+/// A `Result` containing `Some(Registration)` if one of our extrinsics registered the hotkey in this block,
+/// `None` otherwise, or an `Err` if the block could not be inspected or one of our extrinsics failed with an
+/// error that retrying cannot fix
+async fn find_registration(
+    client: &OnlineClient<SubstrateConfig>,
+    block: &ChainBlock,
+    pending: &mut Vec<PendingExtrinsic>,
+    netuid: u16,
+    hotkey: &AccountId32,
+    failure_counts: &mut BTreeMap<String, u64>,
+) -> Result<Option<Registration>, RegbotError> {
+    let block_number = block.header().number;
+    let extrinsics = block.extrinsics().await?;
+
+    for extrinsic in extrinsics.iter() {
+        let extrinsic = extrinsic?;
+        let tx_hash = extrinsic_hash(extrinsic.bytes());
+        let Some(position) = pending.iter().position(|tx| tx.tx_hash == tx_hash) else {
+            continue;
+        };
+        let events = extrinsic.events().await?;
+        let submitted = pending.remove(position);
+
+        let registered = events
+            .find::<NeuronRegistered>()
+            .filter_map(Result::ok)
+            .find(|event| event.0 == netuid && &event.2 == hotkey);
+
+        match registered {
+            Some(NeuronRegistered(_, uid, _)) => {
+                return Ok(Some(Registration {
+                    block_number,
+                    block_hash: block.hash(),
+                    extrinsic_index: extrinsic.index(),
+                    uid,
+                }));
+            }
+            None => {
+                let failure = events
+                    .iter()
+                    .filter_map(Result::ok)
+                    .find(|event| {
+                        event.pallet_name() == "System" && event.variant_name() == "ExtrinsicFailed"
+                    })
+                    .map(|event| {
+                        DispatchError::decode_from(event.field_bytes(), client.metadata())
+                    });
+
+                let error = match failure {
+                    Some(Ok(dispatch_error)) => RegbotError::from(dispatch_error),
+                    Some(Err(e)) => RegbotError::Other(format!(
+                        "ExtrinsicFailed event could not be decoded: {}",
+                        e
+                    )),
+                    None => {
+                        warn!(
+                            "Extrinsic {} included in block {} without a NeuronRegistered event",
+                            tx_hash, block_number
+                        );
+                        continue;
+                    }
+                };
+
+                let name = match &error {
+                    RegbotError::Dispatch { pallet, error, .. } => format!("{}::{}", pallet, error),
+                    other => other.to_string(),
+                };
+                let count = failure_counts.entry(name.clone()).or_insert(0);
+                *count += 1;
+
+                let docs = match &error {
+                    RegbotError::Dispatch { docs, .. } if !docs.is_empty() => docs.as_str(),
+                    _ => "no documentation",
+                };
+                warn!(
+                    "❌ Extrinsic {} failed in block {} (targeted {}): {} - {} ({} failures of this type so far)",
+                    tx_hash, block_number, submitted.submitted_at_block, name, docs, count
+                );
+
+                if error.retry_policy() == RetryPolicy::Abort {
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Retrieves the recycle cost for a given network UID at a specific block
 ///
 /// # Arguments
 ///
 /// * `client` - A reference to the blockchain client
 /// * `netuid` - The network UID to check
+/// * `block_hash` - The hash of the block whose storage should be read
 ///
 /// # Returns
 ///
 /// A `Result` containing the recycle cost as a `u64` if successful, or an `Err` if retrieval fails
-#[allow(dead_code)]
 async fn get_recycle_cost(
     client: &OnlineClient<SubstrateConfig>,
     netuid: u16,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    let latest_block = client.blocks().at_latest().await?;
+    block_hash: H256,
+) -> Result<u64, RegbotError> {
     let burn_key = subxt::storage::dynamic(
         "SubtensorModule",
         "Burn",
@@ -205,7 +1522,7 @@ async fn get_recycle_cost(
     );
     let burn_cost: u64 = client
         .storage()
-        .at(latest_block.hash())
+        .at(block_hash)
         .fetch(&burn_key)
         .await?
         .ok_or_else(|| "Burn value not found for the given netuid".to_string())?
@@ -214,33 +1531,234 @@ async fn get_recycle_cost(
     Ok(burn_cost)
 }
 
-// TODO: Return UID of the registered neuron
+/// Retrieves the registration counts and caps of a subnet at a specific block
+///
+/// # Arguments
+///
+/// * `client` - A reference to the blockchain client
+/// * `netuid` - The network UID to check
+/// * `block_hash` - The hash of the block whose storage should be read
+///
+/// # Returns
+///
+/// A `Result` containing the `RegistrationWindow`, or an `Err` if retrieval fails
+async fn get_registration_window(
+    client: &OnlineClient<SubstrateConfig>,
+    netuid: u16,
+    block_hash: H256,
+) -> Result<RegistrationWindow, RegbotError> {
+    let storage = client.storage().at(block_hash);
+    let fetch = |name: &'static str| {
+        let key =
+            subxt::storage::dynamic("SubtensorModule", name, vec![Value::u128(netuid as u128)]);
+        let storage = storage.clone();
+        async move { Ok::<_, RegbotError>(storage.fetch_or_default(&key).await?) }
+    };
+    let (this_interval, target_per_interval, last_adjustment_block, adjustment_interval) = tokio::try_join!(
+        fetch("RegistrationsThisInterval"),
+        fetch("TargetRegistrationsPerInterval"),
+        fetch("LastAdjustmentBlock"),
+        fetch("AdjustmentInterval")
+    )?;
+
+    Ok(RegistrationWindow {
+        this_interval: this_interval.as_type::<u16>()?,
+        target_per_interval: target_per_interval.as_type::<u16>()?,
+        last_adjustment_block: last_adjustment_block.as_type::<u64>()?,
+        adjustment_interval: adjustment_interval.as_type::<u16>()?,
+    })
+}
+
 /// Main function to run the registration script
+///
+/// Exits with the code of the error that stopped the bot, see `RegbotError::exit_code`.
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> ExitCode {
     // Initialize logging with INFO level
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     info!("Starting registration script...");
 
-    // Parse configuration parameters
-    let params: RegistrationParams = parse_config()?;
-
-    // Attempt to register hotkey
-    if let Err(e) = register_hotkey(&params).await {
-        error!("Error during registration: {}", e);
-        return Err(e);
+    match run().await {
+        Ok(()) => {
+            info!("Registration process completed successfully.");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("Error during registration: {}", e);
+            ExitCode::from(e.exit_code())
+        }
     }
+}
 
-    info!("Registration process completed successfully.");
+/// Generates the hotkeys requested with `regbot keygen`
+///
+/// # Arguments
+///
+/// * `keygen` - The `keygen` parameters
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` once every keyfile is written, or an `Err` if one cannot be
+fn generate_hotkeys(keygen: &KeygenParams) -> Result<(), RegbotError> {
+    let wallet_path = wallet::expand_home(&keygen.wallet_path);
+    for name in &keygen.hotkeys {
+        let hotkey = generate_hotkey(&wallet_path, &keygen.wallet_name, name)
+            .map_err(|e| RegbotError::Config(e.to_string()))?;
+        info!(
+            "🔑 Generated hotkey {} ({}) in wallet {}",
+            name, hotkey.account, keygen.wallet_name
+        );
+    }
     Ok(())
 }
 
-/// Parses configuration from either a config file or command line arguments
+/// Loads the configuration and keys, then runs the registration until it completes or fails
 ///
 /// # Returns
 ///
-/// A `Result` containing `RegistrationParams` if parsing is successful, or an `Err` if it fails
-fn parse_config() -> Result<RegistrationParams, Box<dyn std::error::Error>> {
-    Ok(RegistrationParams::parse())
+/// A `Result` which is `Ok` once the hotkey is registered, or an `Err` containing the `RegbotError` that stopped the bot
+async fn run() -> Result<(), RegbotError> {
+    // Parse configuration parameters
+    let mut params: RegistrationParams =
+        match parse_config().map_err(|e| RegbotError::Config(e.to_string()))? {
+            Mode::Register(params) => *params,
+            Mode::Subcommand(Subcommands::Keygen(keygen)) => return generate_hotkeys(&keygen),
+        };
+
+    // Validate the slot schedule and tips before any password prompt
+    let schedule = SlotSchedule::from_params(&params).map_err(RegbotError::Config)?;
+    let tips = TipPolicy::from_params(&params).map_err(RegbotError::Config)?;
+
+    let wallet_path = wallet::expand_home(&params.wallet_path);
+    let mut source: Option<Box<dyn HotkeySource>> = match (&params.hotkeys_file, params.auto_hotkey)
+    {
+        (Some(path), _) => {
+            let state_path = params
+                .hotkeys_state
+                .clone()
+                .unwrap_or_else(|| default_state_path(path));
+            let queue = HotkeyQueue::load(path, &state_path, &wallet_path)
+                .map_err(|e| RegbotError::Config(e.to_string()))?;
+            if queue.remaining() == 0 {
+                info!(
+                    "✅ Every hotkey of {} is registered, nothing to do",
+                    path.display()
+                );
+                return Ok(());
+            }
+            Some(Box::new(queue))
+        }
+        (None, Some(count)) => {
+            // `--auto-hotkey` requires `--wallet-name`; the hotkeys go to the first wallet given
+            let wallet_name = params.wallet_name.first().cloned().unwrap_or_default();
+            Some(Box::new(HotkeyGenerator::new(
+                wallet_path,
+                wallet_name,
+                count,
+            )))
+        }
+        (None, None) => None,
+    };
+
+    // Each coldkey takes its first hotkey from the source, which `--auto-hotkey` writes to the wallet, so make sure
+    // the slots go round before any is taken
+    if source.is_some() {
+        let coldkeys = if params.coldkey.is_empty() {
+            params.wallet_name.len()
+        } else {
+            params.coldkey.len()
+        };
+        schedule.split(coldkeys).map_err(RegbotError::Config)?;
+    }
+
+    // Derive the keypairs once; the secret strings are discarded afterwards
+    let pairs =
+        load_keypairs(&mut params, &mut source).map_err(|e| RegbotError::Config(e.to_string()))?;
+    if pairs.is_empty() {
+        return Err(RegbotError::Config("No hotkey to register".to_string()));
+    }
+
+    // Attempt to register every hotkey
+    let outcomes = register_hotkeys(&params, &schedule, &tips, pairs, &mut source).await?;
+
+    let mut first_error = None;
+    for (hotkey, outcome) in outcomes {
+        match outcome {
+            Ok(RegistrationOutcome::Registered(registration)) => info!(
+                "🎉 Hotkey {} registered with UID {} on netuid {} in block {} (hash: {}, extrinsic index: {})",
+                hotkey,
+                registration.uid,
+                params.netuid,
+                registration.block_number,
+                registration.block_hash,
+                registration.extrinsic_index
+            ),
+            Ok(RegistrationOutcome::AlreadyRegistered { uid }) => info!(
+                "✅ Hotkey {} is already registered on netuid {} with UID {}, nothing to do",
+                hotkey, params.netuid, uid
+            ),
+            Err(e) => {
+                error!("Registration of hotkey {} failed: {}", hotkey, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::ext::codec::Encode;
+    use subxt::ext::subxt_core::tx::Transaction;
+
+    fn window(this_interval: u16, target_per_interval: u16) -> RegistrationWindow {
+        RegistrationWindow {
+            this_interval,
+            target_per_interval,
+            last_adjustment_block: 100,
+            adjustment_interval: 50,
+        }
+    }
+
+    #[test]
+    fn interval_holds_three_times_its_target() {
+        assert_eq!(window(5, 2).full(120), None);
+        assert!(window(6, 2).full(120).is_some());
+        assert!(window(7, 2).full(120).is_some());
+        assert!(window(0, 0).full(120).is_some());
+    }
+
+    #[test]
+    fn full_interval_reopens_once_reset() {
+        let full = window(6, 2);
+        assert!(full.full(100).is_some());
+        // The last block before `LastAdjustmentBlock + AdjustmentInterval` still counts against the full interval
+        assert!(full.full(149).is_some());
+        assert_eq!(full.full(150), None);
+        assert_eq!(full.full(400), None);
+    }
+
+    #[test]
+    fn reason_names_the_reset_block() {
+        let reason = window(6, 2).full(120).unwrap();
+        assert_eq!(
+            reason,
+            "6 of 6 registrations this interval, which resets at block 150"
+        );
+    }
+
+    #[test]
+    fn block_extrinsic_hash_matches_the_submission_hash() {
+        let body: Vec<u8> = (0..150).map(|byte| byte as u8).collect();
+        // What `SubmittableExtrinsic::hash` hashes: the extrinsic with its compact length prefix
+        let submitted = Transaction::<SubstrateConfig>::from_bytes(body.encode());
+        assert_eq!(extrinsic_hash(&body), submitted.hash());
+        assert_ne!(
+            extrinsic_hash(&body),
+            H256(subxt::ext::sp_core::blake2_256(&body))
+        );
+    }
 }
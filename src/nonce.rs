@@ -0,0 +1,249 @@
+//! Local tracking of coldkey nonces, so back-to-back submissions and pairs sharing a coldkey never reuse a nonce.
+//!
+//! Each coldkey's next nonce is read once with `system_accountNextIndex` and then counted up locally as extrinsics
+//! are submitted. Every block it is reconciled with the account nonce on chain: nonces below it were included,
+//! and a nonce that was neither included nor is still in flight leaves a gap that would hold every later
+//! extrinsic in the pool's future queue, so the tracker falls back to the chain nonce.
+
+use crate::error::RegbotError;
+use log::{info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::utils::AccountId32;
+use subxt::SubstrateConfig;
+
+/// The nonce state of one coldkey
+#[derive(Debug, Default)]
+struct AccountNonces {
+    /// Nonce of the next extrinsic to sign
+    next: u64,
+    /// Nonces of our extrinsics submitted but not yet included or dropped
+    in_flight: BTreeSet<u64>,
+    /// Whether `next` was read from the node and may count extrinsics already in its pool
+    read_from_pool: bool,
+}
+
+/// Next nonces and nonces in flight, per coldkey
+#[derive(Debug, Default)]
+pub struct NonceTracker {
+    accounts: BTreeMap<AccountId32, AccountNonces>,
+}
+
+impl NonceTracker {
+    /// Creates a tracker that knows no coldkey yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the nonce the next extrinsic of a coldkey signs with, reading it from the node if unknown
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc` - The RPC methods of the active endpoint
+    /// * `account` - The coldkey account
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the nonce, or an `Err` if it had to be read and the request failed
+    pub async fn next(
+        &mut self,
+        rpc: &LegacyRpcMethods<SubstrateConfig>,
+        account: &AccountId32,
+    ) -> Result<u64, RegbotError> {
+        if let Some(nonces) = self.accounts.get(account) {
+            return Ok(nonces.next);
+        }
+
+        // Counts the extrinsics of the coldkey already in the node's pool, unlike the nonce in state
+        let next = rpc.system_account_next_index(account).await?;
+        info!("🔢 Next nonce of coldkey {} is {}", account, next);
+        self.accounts.insert(
+            account.clone(),
+            AccountNonces {
+                next,
+                in_flight: BTreeSet::new(),
+                read_from_pool: true,
+            },
+        );
+        Ok(next)
+    }
+
+    /// Returns the locally known next nonce of a coldkey, without contacting the node
+    pub fn peek(&self, account: &AccountId32) -> Option<u64> {
+        self.accounts.get(account).map(|nonces| nonces.next)
+    }
+
+    /// Records an extrinsic accepted by the pool, moving the coldkey on to the following nonce
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The coldkey account
+    /// * `nonce` - The nonce the extrinsic was signed with
+    pub fn submitted(&mut self, account: &AccountId32, nonce: u64) {
+        let nonces = self.accounts.entry(account.clone()).or_default();
+        nonces.in_flight.insert(nonce);
+        nonces.next = nonces.next.max(nonce + 1);
+    }
+
+    /// Records an extrinsic that left the pool without being included, e.g. once its mortal era ended
+    ///
+    /// The next reconciliation reuses its nonce if no later one was included.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The coldkey account
+    /// * `nonce` - The nonce the extrinsic was signed with
+    pub fn dropped(&mut self, account: &AccountId32, nonce: u64) {
+        if let Some(nonces) = self.accounts.get_mut(account) {
+            nonces.in_flight.remove(&nonce);
+        }
+    }
+
+    /// Forgets a coldkey after the pool rejected its nonce, so the next nonce is read from the node again
+    pub fn forget(&mut self, account: &AccountId32) {
+        self.accounts.remove(account);
+    }
+
+    /// Reconciles a coldkey with its account nonce on chain
+    ///
+    /// Nonces below the chain nonce were included. When the chain nonce is neither in flight nor the next
+    /// nonce, a gap stalls every extrinsic above it, so they are given up and the chain nonce is used next.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The coldkey account
+    /// * `chain_nonce` - The account nonce in the state of the latest block
+    pub fn reconcile(&mut self, account: &AccountId32, chain_nonce: u64) {
+        let Some(nonces) = self.accounts.get_mut(account) else {
+            return;
+        };
+        if std::mem::take(&mut nonces.read_from_pool) {
+            // Nonces between the chain nonce and the one read from the node belong to extrinsics in its pool
+            nonces.in_flight.extend(chain_nonce..nonces.next);
+        }
+
+        nonces.in_flight.retain(|nonce| *nonce >= chain_nonce);
+        if nonces.next < chain_nonce {
+            // Another signer used the coldkey, or our extrinsics were included before we saw them
+            nonces.next = chain_nonce;
+            return;
+        }
+
+        if nonces.next > chain_nonce && !nonces.in_flight.contains(&chain_nonce) {
+            warn!(
+                "🕳️ Nonce gap for coldkey {}: nonce {} is no longer in flight, giving up nonces {:?} and resuming at {}",
+                account, chain_nonce, nonces.in_flight, chain_nonce
+            );
+            nonces.in_flight.clear();
+            nonces.next = chain_nonce;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coldkey() -> AccountId32 {
+        AccountId32::from([1u8; 32])
+    }
+
+    fn tracker_at(next: u64, read_from_pool: bool) -> NonceTracker {
+        let mut tracker = NonceTracker::new();
+        tracker.accounts.insert(
+            coldkey(),
+            AccountNonces {
+                next,
+                in_flight: BTreeSet::new(),
+                read_from_pool,
+            },
+        );
+        tracker
+    }
+
+    #[test]
+    fn submissions_count_up_locally() {
+        let mut tracker = tracker_at(5, false);
+        tracker.submitted(&coldkey(), 5);
+        tracker.submitted(&coldkey(), 6);
+        assert_eq!(tracker.peek(&coldkey()), Some(7));
+
+        // Both still in flight: nothing to correct
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(7));
+
+        // Both included
+        tracker.reconcile(&coldkey(), 7);
+        assert_eq!(tracker.peek(&coldkey()), Some(7));
+    }
+
+    #[test]
+    fn dropped_nonce_leaves_a_gap_that_is_refilled() {
+        let mut tracker = tracker_at(5, false);
+        for nonce in 5..8 {
+            tracker.submitted(&coldkey(), nonce);
+        }
+        tracker.dropped(&coldkey(), 5);
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(5));
+
+        // The extrinsics above the gap are given up, so dropping one of them later changes nothing
+        tracker.dropped(&coldkey(), 6);
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(5));
+    }
+
+    #[test]
+    fn dropped_nonce_below_an_included_one_is_no_gap() {
+        let mut tracker = tracker_at(5, false);
+        tracker.submitted(&coldkey(), 5);
+        tracker.submitted(&coldkey(), 6);
+        tracker.dropped(&coldkey(), 5);
+        tracker.reconcile(&coldkey(), 6);
+        assert_eq!(tracker.peek(&coldkey()), Some(7));
+    }
+
+    #[test]
+    fn replacement_keeps_the_nonce_in_flight() {
+        let mut tracker = tracker_at(5, false);
+        tracker.submitted(&coldkey(), 5);
+        // A replacement is signed with the nonce of the extrinsic it replaces
+        tracker.submitted(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(6));
+
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(6));
+        tracker.reconcile(&coldkey(), 6);
+        assert_eq!(tracker.peek(&coldkey()), Some(6));
+    }
+
+    #[test]
+    fn chain_ahead_of_the_tracker_wins() {
+        let mut tracker = tracker_at(5, false);
+        tracker.submitted(&coldkey(), 5);
+        tracker.reconcile(&coldkey(), 9);
+        assert_eq!(tracker.peek(&coldkey()), Some(9));
+    }
+
+    #[test]
+    fn nonces_read_from_the_pool_are_in_flight() {
+        // The node counted two extrinsics of the coldkey in its pool above the chain nonce
+        let mut tracker = tracker_at(7, true);
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(7));
+
+        // Once the first of them leaves the pool unincluded, the gap is detected
+        tracker.dropped(&coldkey(), 5);
+        tracker.reconcile(&coldkey(), 5);
+        assert_eq!(tracker.peek(&coldkey()), Some(5));
+    }
+
+    #[test]
+    fn forgotten_coldkey_is_unknown() {
+        let mut tracker = tracker_at(5, false);
+        tracker.forget(&coldkey());
+        assert_eq!(tracker.peek(&coldkey()), None);
+        tracker.reconcile(&coldkey(), 3);
+        assert_eq!(tracker.peek(&coldkey()), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Queue of hotkeys registered one after another from a hotkeys file, with per-hotkey progress persisted so a
+//! restarted bot resumes where it left off.
+//!
+//! The hotkeys file lists one hotkey per line; blank lines and lines starting with `#` are ignored. A line is
+//! either an SS58 address, a `pub:0x` hex public key, `wallet:<wallet>/<hotkey>` for a btcli hotkey under
+//! `--wallet-path`, or a secret URI. Only the public key is ever needed, so addresses keep hotkey secrets off the
+//! registration host.
+
+use crate::keys::{self, Hotkey};
+use crate::wallet;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use subxt::utils::AccountId32;
+use zeroize::Zeroizing;
+
+/// Progress of a hotkey from the hotkeys file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum HotkeyStatus {
+    /// A coldkey is working on registering the hotkey
+    Pending,
+    /// The hotkey is registered on the subnet
+    Registered { uid: u16 },
+    /// The registration stopped with an error; the hotkey is retried after a restart
+    Failed { error: String },
+}
+
+/// Hotkeys handed out to coldkeys one at a time, each coldkey taking the next after a registration
+pub trait HotkeySource {
+    /// Takes the next hotkey to register
+    fn next(&mut self) -> Option<Hotkey>;
+
+    /// Returns the hotkeys already known to be waiting, so they can be checked before the first submission
+    fn waiting(&self) -> Vec<&Hotkey>;
+
+    /// Returns the number of hotkeys still to come
+    fn remaining(&self) -> usize;
+
+    /// Records how the registration of a hotkey ended
+    fn record(&mut self, hotkey: &AccountId32, status: HotkeyStatus);
+}
+
+/// Hotkeys waiting for registration, in file order, with the persisted progress of every hotkey
+pub struct HotkeyQueue {
+    queue: VecDeque<Hotkey>,
+    states: BTreeMap<String, HotkeyStatus>,
+    state_path: PathBuf,
+}
+
+impl HotkeyQueue {
+    /// Reads the hotkeys file and the state file, queueing every hotkey not registered yet
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the hotkeys file
+    /// * `state_path` - Path to the state file, created on the first update if missing
+    /// * `wallet_path` - The directory containing btcli wallets, for `wallet:` lines
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `HotkeyQueue`, or an `Err` if a file cannot be read or a line is invalid
+    pub fn load(
+        path: &Path,
+        state_path: &Path,
+        wallet_path: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let display = path.display();
+        // The file may hold secret URIs, so wipe the raw contents once parsed
+        let contents = std::fs::read_to_string(path)
+            .map(Zeroizing::new)
+            .map_err(|e| format!("Failed to read hotkeys file {}: {}", display, e))?;
+
+        let states: BTreeMap<String, HotkeyStatus> = match std::fs::read_to_string(state_path) {
+            Ok(state) => serde_json::from_str(&state)
+                .map_err(|e| format!("Invalid state file {}: {}", state_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(
+                    format!("Failed to read state file {}: {}", state_path.display(), e).into(),
+                )
+            }
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let hotkey = parse_hotkey(line, wallet_path)
+                .map_err(|e| format!("Line {} of hotkeys file {}: {}", number + 1, display, e))?;
+            let account = &hotkey.account;
+            if !seen.insert(account.clone()) {
+                warn!("Hotkey {} is listed more than once in {}", account, display);
+                continue;
+            }
+
+            match states.get(&account.to_string()) {
+                Some(HotkeyStatus::Registered { uid }) => {
+                    info!("✅ Hotkey {} already registered with UID {}", account, uid)
+                }
+                Some(HotkeyStatus::Failed { error }) => {
+                    info!(
+                        "🔁 Retrying hotkey {}, which failed before: {}",
+                        account, error
+                    );
+                    queue.push_back(hotkey);
+                }
+                _ => queue.push_back(hotkey),
+            }
+        }
+        info!(
+            "📋 {} of {} hotkeys from {} left to register",
+            queue.len(),
+            seen.len(),
+            display
+        );
+
+        Ok(Self {
+            queue,
+            states,
+            state_path: state_path.to_path_buf(),
+        })
+    }
+}
+
+impl HotkeySource for HotkeyQueue {
+    /// Takes the next hotkey to register, marking it pending
+    fn next(&mut self) -> Option<Hotkey> {
+        let hotkey = self.queue.pop_front()?;
+        self.record(&hotkey.account, HotkeyStatus::Pending);
+        Some(hotkey)
+    }
+
+    fn waiting(&self) -> Vec<&Hotkey> {
+        self.queue.iter().collect()
+    }
+
+    fn remaining(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Records the progress of a hotkey and writes the state file
+    ///
+    /// The file is replaced atomically, so a crash never leaves it half written. A failed write is logged and
+    /// does not stop the registration.
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkey` - The account ID of the hotkey
+    /// * `status` - Its new status
+    fn record(&mut self, hotkey: &AccountId32, status: HotkeyStatus) {
+        self.states.insert(hotkey.to_string(), status);
+
+        let temp_path = self.state_path.with_extension("tmp");
+        let written = serde_json::to_string_pretty(&self.states)
+            .map_err(|e| e.to_string())
+            .and_then(|state| std::fs::write(&temp_path, state).map_err(|e| e.to_string()))
+            .and_then(|_| std::fs::rename(&temp_path, &self.state_path).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            warn!(
+                "Failed to write state file {}: {}",
+                self.state_path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Returns the state file used for a hotkeys file when `--hotkeys-state` is not given
+///
+/// # Arguments
+///
+/// * `path` - Path to the hotkeys file
+///
+/// # Returns
+///
+/// The hotkeys file path with `.state.json` appended
+pub fn default_state_path(path: &Path) -> PathBuf {
+    let mut state_path = path.as_os_str().to_owned();
+    state_path.push(".state.json");
+    PathBuf::from(state_path)
+}
+
+/// Parses a line of the hotkeys file
+///
+/// # Arguments
+///
+/// * `line` - An SS58 address, a `pub:0x` hex public key, `wallet:<wallet>/<hotkey>`, or a secret URI
+/// * `wallet_path` - The directory containing btcli wallets
+///
+/// # Returns
+///
+/// A `Result` containing the `Hotkey`, or an `Err` if the line is not a valid hotkey
+fn parse_hotkey(line: &str, wallet_path: &Path) -> Result<Hotkey, Box<dyn std::error::Error>> {
+    if let Some(entry) = line.strip_prefix("wallet:") {
+        let (wallet_name, hotkey_name) = entry
+            .split_once('/')
+            .ok_or("expected `wallet:<wallet>/<hotkey>`")?;
+        let hotkey = wallet::load_hotkey(wallet_path, wallet_name, hotkey_name)?;
+        return Ok(Hotkey::from(&hotkey));
+    }
+
+    Ok(keys::parse_hotkey(line)?)
+}
@@ -0,0 +1,185 @@
+//! Slot schedules deciding which blocks registrations are submitted on.
+
+use crate::config::RegistrationParams;
+use std::fmt;
+
+/// The blocks a registration is submitted on: those whose `block_number % modulus` is one of `slots`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotSchedule {
+    modulus: u32,
+    slots: Vec<u32>,
+}
+
+impl SlotSchedule {
+    /// Creates a schedule, checking every slot can actually occur
+    ///
+    /// # Arguments
+    ///
+    /// * `modulus` - The block number modulus, at least 1
+    /// * `slots` - The residues to submit on, each below `modulus`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SlotSchedule`, or an `Err` describing the invalid slot
+    pub fn new(modulus: u32, slots: &[u32]) -> Result<Self, String> {
+        if modulus == 0 {
+            return Err("slot modulus must be at least 1".to_string());
+        }
+        if slots.is_empty() {
+            return Err("at least one slot is required".to_string());
+        }
+        if let Some(slot) = slots.iter().find(|slot| **slot >= modulus) {
+            return Err(format!(
+                "slot {} can never occur with slot modulus {} (slots range from 0 to {})",
+                slot,
+                modulus,
+                modulus - 1
+            ));
+        }
+
+        let mut slots = slots.to_vec();
+        slots.sort_unstable();
+        slots.dedup();
+        Ok(Self { modulus, slots })
+    }
+
+    /// Creates a schedule submitting on every block
+    pub fn every_block() -> Self {
+        Self {
+            modulus: 1,
+            slots: vec![0],
+        }
+    }
+
+    /// Builds the schedule from `--every-block`, or `--slot-modulus` and `--slots`
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The registration parameters
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SlotSchedule`, or an `Err` describing the invalid slot
+    pub fn from_params(params: &RegistrationParams) -> Result<Self, String> {
+        if params.every_block {
+            return Ok(Self::every_block());
+        }
+        Self::new(params.slot_modulus, &params.slots)
+    }
+
+    /// Deals the slots out to several key pairs in turn
+    ///
+    /// Every pair needs at least one slot; with every block targeted (`--every-block`), each pair submits on every block.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of key pairs
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing one `SlotSchedule` per pair, or an `Err` if there are fewer slots than pairs
+    pub fn split(&self, count: usize) -> Result<Vec<Self>, String> {
+        if self.modulus == 1 {
+            return Ok(vec![self.clone(); count]);
+        }
+        if self.slots.len() < count {
+            return Err(format!(
+                "{} key pairs need at least {} slots, but only {} are scheduled ({})",
+                count,
+                count,
+                self.slots.len(),
+                self
+            ));
+        }
+
+        Ok((0..count)
+            .map(|pair| Self {
+                modulus: self.modulus,
+                slots: self
+                    .slots
+                    .iter()
+                    .skip(pair)
+                    .step_by(count)
+                    .copied()
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Returns the slot a block falls in
+    pub fn slot_of(&self, block_number: u32) -> u32 {
+        block_number % self.modulus
+    }
+
+    /// Returns whether a registration should be submitted on a block
+    pub fn matches(&self, block_number: u32) -> bool {
+        self.slots.contains(&self.slot_of(block_number))
+    }
+
+    /// Returns whether every block is targeted, leaving no idle blocks in between
+    pub fn is_every_block(&self) -> bool {
+        self.slots.len() as u32 == self.modulus
+    }
+}
+
+impl fmt::Display for SlotSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_every_block() {
+            return f.write_str("every block");
+        }
+        let slots: Vec<String> = self.slots.iter().map(u32::to_string).collect();
+        write!(
+            f,
+            "blocks where block_number % {} is {}",
+            self.modulus,
+            slots.join(" or ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_checked_against_the_modulus() {
+        assert!(SlotSchedule::new(0, &[0]).is_err());
+        assert!(SlotSchedule::new(3, &[]).is_err());
+        assert!(SlotSchedule::new(3, &[3]).is_err());
+        assert_eq!(
+            SlotSchedule::new(3, &[2, 0, 2]).unwrap(),
+            SlotSchedule::new(3, &[0, 2]).unwrap()
+        );
+    }
+
+    #[test]
+    fn blocks_match_their_slot() {
+        let schedule = SlotSchedule::new(3, &[2]).unwrap();
+        assert_eq!(schedule.slot_of(7), 1);
+        assert!(schedule.matches(8));
+        assert!(!schedule.matches(9));
+        assert!(!schedule.is_every_block());
+        assert!(SlotSchedule::new(2, &[0, 1]).unwrap().is_every_block());
+    }
+
+    #[test]
+    fn slots_are_dealt_in_turn() {
+        let schedule = SlotSchedule::new(6, &[0, 1, 2, 3, 4]).unwrap();
+        let split = schedule.split(2).unwrap();
+        assert_eq!(split[0], SlotSchedule::new(6, &[0, 2, 4]).unwrap());
+        assert_eq!(split[1], SlotSchedule::new(6, &[1, 3]).unwrap());
+    }
+
+    #[test]
+    fn every_pair_gets_a_slot() {
+        let schedule = SlotSchedule::new(3, &[0, 2]).unwrap();
+        assert_eq!(schedule.split(1).unwrap(), vec![schedule.clone()]);
+        assert!(schedule.split(3).is_err());
+    }
+
+    #[test]
+    fn every_block_is_shared() {
+        let split = SlotSchedule::every_block().split(3).unwrap();
+        assert_eq!(split, vec![SlotSchedule::every_block(); 3]);
+    }
+}
@@ -0,0 +1,301 @@
+//! Signing of registration extrinsics and their submission to one or several RPC endpoints.
+
+use crate::blocks::ChainBlock;
+use crate::connection::EndpointPool;
+use crate::error::RegbotError;
+use crate::watch;
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::{debug, info, warn};
+use scale_value::Composite;
+use subxt::config::DefaultExtrinsicParamsBuilder;
+use subxt::ext::sp_core::sr25519;
+use subxt::tx::{DefaultPayload, PairSigner, SubmittableExtrinsic};
+use subxt::utils::H256;
+use subxt::{OnlineClient, SubstrateConfig};
+use tokio::task::JoinHandle;
+
+/// A submission in flight to one endpoint, labelled with the endpoint URL
+type Submission = (String, JoinHandle<Result<H256, RegbotError>>);
+
+/// Shortest mortality period the runtime accepts, in blocks
+const MIN_MORTALITY_PERIOD: u64 = 4;
+
+/// Longest mortality period the runtime accepts, in blocks
+const MAX_MORTALITY_PERIOD: u64 = 1 << 16;
+
+/// A signed registration extrinsic
+pub type SignedExtrinsic = SubmittableExtrinsic<SubstrateConfig, OnlineClient<SubstrateConfig>>;
+
+/// Whether a pre-signed extrinsic can still be submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresignState {
+    /// Nonce, runtime and mortality are all still valid
+    Current,
+    /// The next nonce of the coldkey moved since signing
+    NonceChanged,
+    /// The runtime was upgraded since signing; the client metadata must be refreshed too
+    RuntimeUpgraded,
+    /// The mortality checkpoint is too old for the extrinsic to stay valid long enough
+    Expiring,
+}
+
+/// Returns the mortality period actually used for a requested number of blocks
+///
+/// Mortal eras only come in powers of two between 4 and 65536 blocks, so the request is rounded up and clamped.
+///
+/// # Arguments
+///
+/// * `blocks` - The requested mortality, in blocks
+///
+/// # Returns
+///
+/// The mortality period, in blocks
+pub fn mortality_period(blocks: u64) -> u64 {
+    blocks
+        .checked_next_power_of_two()
+        .unwrap_or(MAX_MORTALITY_PERIOD)
+        .clamp(MIN_MORTALITY_PERIOD, MAX_MORTALITY_PERIOD)
+}
+
+/// A registration extrinsic signed ahead of its slot, so only the submission is left when the slot arrives
+pub struct PresignedExtrinsic {
+    signed: SignedExtrinsic,
+    nonce: u64,
+    spec_version: u32,
+    transaction_version: u32,
+    checkpoint_block: u32,
+    mortality_period: u64,
+    tip: u64,
+}
+
+impl PresignedExtrinsic {
+    /// Signs the payload with the given nonce and a mortal era anchored on the observed block
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client of the active endpoint
+    /// * `payload` - The `burned_register` call
+    /// * `signer` - The coldkey signer
+    /// * `nonce` - The next nonce of the coldkey, from the nonce tracker
+    /// * `checkpoint` - The block just observed, which the mortality is anchored on
+    /// * `mortality_blocks` - The number of blocks the extrinsic stays valid for, see `mortality_period`
+    /// * `tip` - The tip paid to the block author, in rao
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the pre-signed extrinsic, or an `Err` if the chain state could not be read
+    pub async fn build(
+        client: &OnlineClient<SubstrateConfig>,
+        payload: &DefaultPayload<Composite<()>>,
+        signer: &PairSigner<SubstrateConfig, sr25519::Pair>,
+        nonce: u64,
+        checkpoint: &ChainBlock,
+        mortality_blocks: u64,
+        tip: u64,
+    ) -> Result<Self, RegbotError> {
+        let mortality_period = mortality_period(mortality_blocks);
+        let params = DefaultExtrinsicParamsBuilder::new()
+            .nonce(nonce)
+            .mortal(checkpoint.header(), mortality_period)
+            .tip(tip as u128)
+            .build();
+        let signed = client.tx().create_signed_offline(payload, signer, params)?;
+        let runtime_version = client.runtime_version();
+
+        Ok(Self {
+            signed,
+            nonce,
+            spec_version: runtime_version.spec_version,
+            transaction_version: runtime_version.transaction_version,
+            checkpoint_block: checkpoint.number(),
+            mortality_period,
+            tip,
+        })
+    }
+
+    /// Checks the nonce, runtime version and mortality the extrinsic was signed with against the chain
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client of the active endpoint
+    /// * `block` - The block just observed
+    /// * `nonce` - The next nonce of the coldkey, from the nonce tracker
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `PresignState`, or an `Err` if the chain state could not be read
+    pub async fn check(
+        &self,
+        client: &OnlineClient<SubstrateConfig>,
+        block: &ChainBlock,
+        nonce: u64,
+    ) -> Result<PresignState, RegbotError> {
+        // Rebuild halfway through the mortality period so the extrinsic stays valid through its slot
+        if block.number() as u64 >= self.checkpoint_block as u64 + self.mortality_period / 2 {
+            return Ok(PresignState::Expiring);
+        }
+
+        let runtime_version = client.backend().current_runtime_version().await?;
+        if runtime_version.spec_version != self.spec_version
+            || runtime_version.transaction_version != self.transaction_version
+        {
+            return Ok(PresignState::RuntimeUpgraded);
+        }
+
+        if nonce != self.nonce {
+            return Ok(PresignState::NonceChanged);
+        }
+
+        Ok(PresignState::Current)
+    }
+
+    /// Returns the nonce the extrinsic was signed with
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Returns the number of the block the mortality is anchored on
+    pub fn checkpoint_block(&self) -> u32 {
+        self.checkpoint_block
+    }
+
+    /// Returns the tip paid with the extrinsic, in rao
+    pub fn tip(&self) -> u64 {
+        self.tip
+    }
+
+    /// Returns the number of the last block the extrinsic can be included in
+    pub fn valid_until(&self) -> u32 {
+        (self.checkpoint_block as u64 + self.mortality_period - 1).min(u32::MAX as u64) as u32
+    }
+
+    /// Consumes the pre-signed extrinsic, returning the signed bytes ready for submission
+    pub fn into_signed(self) -> SignedExtrinsic {
+        self.signed
+    }
+}
+
+/// Submits a signed registration extrinsic
+///
+/// The extrinsic is submitted to the active endpoint, watched in the background when `watch` is set,
+/// and when `broadcast` is set the same bytes are also pushed to every other endpoint.
+///
+/// # Arguments
+///
+/// * `client` - The client of the active endpoint, used by the lifecycle watcher
+/// * `endpoints` - The endpoint pool, used for broadcasting
+/// * `signed` - The signed extrinsic
+/// * `watch` - Whether to follow the extrinsic lifecycle in the background
+/// * `broadcast` - Whether to submit to every configured endpoint
+/// * `target_block` - The block number the submission is aimed at
+///
+/// # Returns
+///
+/// A `Result` containing the extrinsic hash once any endpoint accepted it, or an `Err` if every endpoint
+/// rejected it
+pub async fn submit_signed(
+    client: &OnlineClient<SubstrateConfig>,
+    endpoints: &EndpointPool,
+    signed: SignedExtrinsic,
+    watch: bool,
+    broadcast: bool,
+    target_block: u32,
+) -> Result<H256, RegbotError> {
+    let tx_hash = signed.hash();
+    let encoded = signed.encoded().to_vec();
+
+    let watch_client = client.clone();
+    let primary = tokio::spawn(async move {
+        if watch {
+            let progress = signed.submit_and_watch().await?;
+            // Follow the lifecycle in the background so the next slot is not delayed
+            watch::spawn_watcher(watch_client, progress, target_block);
+            Ok(tx_hash)
+        } else {
+            Ok(signed.submit().await?)
+        }
+    });
+
+    let mut submissions: Vec<Submission> = vec![(endpoints.active_url().to_string(), primary)];
+    if broadcast {
+        submissions.extend(endpoints.spawn_submissions(&encoded));
+    }
+
+    first_acceptance(tx_hash, submissions).await
+}
+
+/// Waits for the first endpoint to accept an extrinsic
+///
+/// "Already imported" rejections mean another submission reached the node first and count as acceptance.
+/// Submissions still in flight keep running and their outcome is logged.
+///
+/// # Arguments
+///
+/// * `tx_hash` - The hash of the submitted extrinsic
+/// * `submissions` - The submissions in flight; the first one is the active endpoint's
+///
+/// # Returns
+///
+/// A `Result` containing `tx_hash` once accepted, or the active endpoint's error if every endpoint rejected it
+async fn first_acceptance(
+    tx_hash: H256,
+    submissions: Vec<Submission>,
+) -> Result<H256, RegbotError> {
+    let total = submissions.len();
+    let primary_url = submissions[0].0.clone();
+    let mut in_flight: FuturesUnordered<_> = submissions
+        .into_iter()
+        .map(|(url, handle)| async move { (url, flatten(handle.await)) })
+        .collect();
+
+    let mut errors: Vec<(String, RegbotError)> = Vec::new();
+    while let Some((url, result)) = in_flight.next().await {
+        match result {
+            Ok(_) => {
+                if total > 1 {
+                    info!("📣 Tx {} first accepted by {}", tx_hash, url);
+                }
+                break;
+            }
+            Err(e) if e.is_already_imported() => {
+                debug!("Tx {} already known to {}", tx_hash, url);
+                break;
+            }
+            Err(e) => {
+                warn!("Submission of tx {} to {} failed: {}", tx_hash, url, e);
+                errors.push((url, e));
+            }
+        }
+    }
+
+    if errors.len() == total {
+        let position = errors
+            .iter()
+            .position(|(url, _)| *url == primary_url)
+            .unwrap_or(0);
+        return Err(errors.swap_remove(position).1);
+    }
+
+    if !in_flight.is_empty() {
+        tokio::spawn(async move {
+            while let Some((url, result)) = in_flight.next().await {
+                match result {
+                    Ok(_) => debug!("Tx {} also accepted by {}", tx_hash, url),
+                    Err(e) if e.is_already_imported() => {
+                        debug!("Tx {} already known to {}", tx_hash, url)
+                    }
+                    Err(e) => debug!("Tx {} rejected by {}: {}", tx_hash, url, e),
+                }
+            }
+        });
+    }
+
+    Ok(tx_hash)
+}
+
+/// Turns a panicked or cancelled submission task into an error
+fn flatten(
+    joined: Result<Result<H256, RegbotError>, tokio::task::JoinError>,
+) -> Result<H256, RegbotError> {
+    joined.unwrap_or_else(|e| Err(RegbotError::Other(format!("submission task failed: {}", e))))
+}
@@ -0,0 +1,81 @@
+//! Tip policies deciding the tip paid with each registration extrinsic.
+
+use crate::balance::format_tao;
+use crate::config::RegistrationParams;
+use std::fmt;
+
+/// The tip of the first submission, raised by `step` after every missed slot up to `max`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipPolicy {
+    initial: u64,
+    step: u64,
+    max: u64,
+}
+
+impl TipPolicy {
+    /// Creates a tip policy, checking the cap is not below the initial tip
+    ///
+    /// # Arguments
+    ///
+    /// * `initial` - The tip of the first submission, in rao
+    /// * `step` - The amount added after each missed slot, in rao
+    /// * `max` - The highest tip to escalate to, in rao
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `TipPolicy`, or an `Err` if the cap is below the initial tip
+    pub fn new(initial: u64, step: u64, max: u64) -> Result<Self, String> {
+        if max < initial {
+            return Err(format!(
+                "max tip {} is below the initial tip {}",
+                format_tao(max as u128),
+                format_tao(initial as u128)
+            ));
+        }
+        Ok(Self { initial, step, max })
+    }
+
+    /// Builds the policy from `--tip`, `--tip-step` and `--max-tip`
+    ///
+    /// Without `--max-tip` the tip never rises above `--tip`.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The registration parameters
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `TipPolicy`, or an `Err` if the cap is below the initial tip
+    pub fn from_params(params: &RegistrationParams) -> Result<Self, String> {
+        Self::new(
+            params.tip,
+            params.tip_step,
+            params.max_tip.unwrap_or(params.tip),
+        )
+    }
+
+    /// Returns the tip of the first submission for a hotkey
+    pub fn initial(&self) -> u64 {
+        self.initial
+    }
+
+    /// Returns the tip to use after a submission with `tip` missed its slot
+    pub fn escalate(&self, tip: u64) -> u64 {
+        tip.saturating_add(self.step).min(self.max)
+    }
+}
+
+impl fmt::Display for TipPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tip {}", format_tao(self.initial as u128))?;
+        if self.step > 0 && self.max > self.initial {
+            write!(
+                f,
+                ", raised by {} per missed slot up to {}",
+                format_tao(self.step as u128),
+                format_tao(self.max as u128)
+            )?;
+        }
+        Ok(())
+    }
+}
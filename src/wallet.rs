@@ -0,0 +1,182 @@
+//! Loading of coldkeys and hotkeys from Bittensor wallet directories.
+//!
+//! Wallets follow the btcli layout: `<wallet_path>/<name>/coldkey` and `<wallet_path>/<name>/hotkeys/<hotkey>`.
+//! Keyfiles are JSON documents, optionally encrypted with NaCl secretbox under an argon2i derived key.
+
+use crate::secret::SecretString;
+use argon2::{Algorithm, Argon2, Params, Version};
+use crypto_secretbox::aead::{Aead, KeyInit};
+use crypto_secretbox::{Nonce, XSalsa20Poly1305};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use subxt::ext::sp_core::{sr25519, Pair};
+use zeroize::Zeroizing;
+
+/// Environment variable read for the coldkey password before falling back to an interactive prompt
+pub const COLDKEY_PASSWORD_ENV: &str = "REGBOT_COLDKEY_PASSWORD";
+
+/// Prefix of keyfiles encrypted by btcli with NaCl secretbox
+const NACL_PREFIX: &[u8] = b"$NACL";
+
+/// Salt btcli uses when deriving the keyfile encryption key from the password
+const NACL_SALT: [u8; 16] = [
+    0x13, 0x71, 0x83, 0xdf, 0xf1, 0x5a, 0x09, 0xbc, 0x9c, 0x90, 0xb5, 0x51, 0x87, 0x39, 0xe9, 0xb1,
+];
+
+/// Argon2i cost parameters matching libsodium's `OPSLIMIT_SENSITIVE` / `MEMLIMIT_SENSITIVE`
+const NACL_OPS_LIMIT: u32 = 8;
+const NACL_MEM_LIMIT_KIB: u32 = 512 * 1024;
+
+/// Secret material stored in a btcli keyfile
+#[derive(Deserialize)]
+struct Keyfile {
+    #[serde(rename = "secretPhrase")]
+    secret_phrase: Option<SecretString>,
+    #[serde(rename = "secretSeed")]
+    secret_seed: Option<SecretString>,
+}
+
+/// Expands a leading `~` in a path to the user's home directory
+///
+/// # Arguments
+///
+/// * `path` - The path to expand
+///
+/// # Returns
+///
+/// The expanded path, or the input unchanged if it does not start with `~` or `HOME` is unset
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Loads the coldkey of a wallet
+///
+/// # Arguments
+///
+/// * `wallet_path` - The directory containing all wallets
+/// * `wallet_name` - The name of the wallet
+///
+/// # Returns
+///
+/// A `Result` containing the coldkey pair, or an `Err` if the keyfile is missing, cannot be decrypted or is invalid
+pub fn load_coldkey(
+    wallet_path: &Path,
+    wallet_name: &str,
+) -> Result<sr25519::Pair, Box<dyn std::error::Error>> {
+    load_keyfile(&wallet_path.join(wallet_name).join("coldkey"))
+}
+
+/// Loads a hotkey of a wallet
+///
+/// # Arguments
+///
+/// * `wallet_path` - The directory containing all wallets
+/// * `wallet_name` - The name of the wallet
+/// * `hotkey_name` - The name of the hotkey within the wallet
+///
+/// # Returns
+///
+/// A `Result` containing the hotkey pair, or an `Err` if the keyfile is missing, cannot be decrypted or is invalid
+pub fn load_hotkey(
+    wallet_path: &Path,
+    wallet_name: &str,
+    hotkey_name: &str,
+) -> Result<sr25519::Pair, Box<dyn std::error::Error>> {
+    load_keyfile(
+        &wallet_path
+            .join(wallet_name)
+            .join("hotkeys")
+            .join(hotkey_name),
+    )
+}
+
+/// Reads a keyfile, decrypting it if needed, and builds the keypair it describes
+///
+/// # Arguments
+///
+/// * `path` - Path to the keyfile
+///
+/// # Returns
+///
+/// A `Result` containing the keypair, or an `Err` if the keyfile cannot be read, decrypted or parsed
+fn load_keyfile(path: &Path) -> Result<sr25519::Pair, Box<dyn std::error::Error>> {
+    let display = path.display();
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read keyfile {}: {}", display, e))?;
+
+    let data = if let Some(encrypted) = data.strip_prefix(NACL_PREFIX) {
+        let password = keyfile_password(path)?;
+        decrypt_nacl(encrypted, password.expose_secret())
+            .map_err(|e| format!("Failed to decrypt keyfile {}: {}", display, e))?
+    } else {
+        Zeroizing::new(data)
+    };
+
+    let keyfile: Keyfile = serde_json::from_slice(&data).map_err(|e| {
+        format!(
+            "Keyfile {} is not a supported btcli keyfile (legacy encryption formats must be migrated with btcli): {}",
+            display, e
+        )
+    })?;
+
+    let secret = keyfile
+        .secret_phrase
+        .or(keyfile.secret_seed)
+        .ok_or_else(|| format!("Keyfile {} contains no secret phrase or seed", display))?;
+
+    sr25519::Pair::from_string(secret.expose_secret(), None)
+        .map_err(|_| format!("Keyfile {} contains an invalid secret", display).into())
+}
+
+/// Returns the password of an encrypted keyfile, from the environment or an interactive prompt
+///
+/// # Arguments
+///
+/// * `path` - Path to the keyfile, shown in the prompt
+///
+/// # Returns
+///
+/// A `Result` containing the password, or an `Err` if it cannot be read from the terminal
+fn keyfile_password(path: &Path) -> Result<SecretString, Box<dyn std::error::Error>> {
+    if let Ok(password) = std::env::var(COLDKEY_PASSWORD_ENV) {
+        return Ok(password.into());
+    }
+    let password =
+        rpassword::prompt_password(format!("Enter password to unlock {}: ", path.display()))?;
+    Ok(password.into())
+}
+
+/// Decrypts the body of a `$NACL` keyfile
+///
+/// # Arguments
+///
+/// * `encrypted` - The keyfile contents after the `$NACL` prefix: a 24 byte nonce followed by the ciphertext
+/// * `password` - The keyfile password
+///
+/// # Returns
+///
+/// A `Result` containing the decrypted keyfile JSON, or an `Err` if the password is wrong or the data is malformed
+fn decrypt_nacl(
+    encrypted: &[u8],
+    password: &str,
+) -> Result<Zeroizing<Vec<u8>>, Box<dyn std::error::Error>> {
+    if encrypted.len() < 24 {
+        return Err("encrypted data is too short".into());
+    }
+    let (nonce, ciphertext) = encrypted.split_at(24);
+
+    let params = Params::new(NACL_MEM_LIMIT_KIB, NACL_OPS_LIMIT, 1, Some(32))
+        .map_err(|e| format!("invalid key derivation parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2i, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), &NACL_SALT, key.as_mut())
+        .map_err(|e| format!("key derivation failed: {}", e))?;
+
+    XSalsa20Poly1305::new((&*key).into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| "wrong password or corrupted keyfile".into())
+}
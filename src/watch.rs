@@ -0,0 +1,142 @@
+//! Background tracking of submitted extrinsics through the transaction pool lifecycle.
+
+use log::{info, warn};
+use subxt::tx::{TxProgress, TxStatus};
+use subxt::utils::H256;
+use subxt::{OnlineClient, SubstrateConfig};
+use tokio::task::JoinHandle;
+
+/// Message subxt reports for a transaction replaced in the pool by another with the same nonce
+const USURPED_MESSAGE: &str = "Transaction was usurped by another with the same nonce";
+
+/// Follows an extrinsic from the pool until it is finalized or rejected, without blocking the caller
+///
+/// # Arguments
+///
+/// * `client` - The blockchain client, used to resolve block numbers
+/// * `progress` - The progress stream returned by `submit_and_watch`
+/// * `target_block` - The block number the submission was aimed at
+///
+/// # Returns
+///
+/// The handle of the spawned background task
+pub fn spawn_watcher(
+    client: OnlineClient<SubstrateConfig>,
+    mut progress: TxProgress<SubstrateConfig, OnlineClient<SubstrateConfig>>,
+    target_block: u32,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let tx_hash = progress.extrinsic_hash();
+        let mut landed_block: Option<u32> = None;
+
+        while let Some(status) = progress.next().await {
+            match status {
+                Ok(TxStatus::Validated) => {
+                    info!("📥 Tx {} is ready in the pool", tx_hash);
+                }
+                Ok(TxStatus::Broadcasted { num_peers }) => {
+                    info!("📡 Tx {} broadcast to {} peers", tx_hash, num_peers);
+                }
+                Ok(TxStatus::NoLongerInBestBlock) => {
+                    warn!("↩️ Tx {} is no longer in the best block", tx_hash);
+                    landed_block = None;
+                }
+                Ok(TxStatus::InBestBlock(in_block)) => {
+                    landed_block = block_number(&client, in_block.block_hash()).await;
+                    info!(
+                        "📦 Tx {} included in best block {} (targeted {})",
+                        tx_hash,
+                        describe_block(landed_block),
+                        target_block
+                    );
+                }
+                Ok(TxStatus::InFinalizedBlock(in_block)) => {
+                    let finalized_block = block_number(&client, in_block.block_hash()).await;
+                    info!(
+                        "🏁 Tx {} finalized in block {} (targeted {}, {} late)",
+                        tx_hash,
+                        describe_block(finalized_block),
+                        target_block,
+                        describe_delay(finalized_block, target_block)
+                    );
+                    return;
+                }
+                Ok(TxStatus::Error { message }) => {
+                    warn!(
+                        "❌ Tx {} errored (targeted {}, last seen in block {}): {}",
+                        tx_hash,
+                        target_block,
+                        describe_block(landed_block),
+                        message
+                    );
+                    return;
+                }
+                Ok(TxStatus::Invalid { message }) if message == USURPED_MESSAGE => {
+                    info!(
+                        "🔁 Tx {} was usurped by a replacement with the same nonce (targeted {})",
+                        tx_hash, target_block
+                    );
+                    return;
+                }
+                Ok(TxStatus::Invalid { message }) => {
+                    warn!(
+                        "❌ Tx {} became invalid (targeted {}, last seen in block {}): {}",
+                        tx_hash,
+                        target_block,
+                        describe_block(landed_block),
+                        message
+                    );
+                    return;
+                }
+                Ok(TxStatus::Dropped { message }) => {
+                    warn!(
+                        "❌ Tx {} was dropped (targeted {}, last seen in block {}): {}",
+                        tx_hash,
+                        target_block,
+                        describe_block(landed_block),
+                        message
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!(
+                        "Lost track of tx {} (targeted {}): {:?}",
+                        tx_hash, target_block, e
+                    );
+                    return;
+                }
+            }
+        }
+
+        warn!(
+            "Tx {} status stream ended without a final outcome (targeted {}, last seen in block {})",
+            tx_hash,
+            target_block,
+            describe_block(landed_block)
+        );
+    })
+}
+
+/// Resolves a block hash to its number, logging failures
+async fn block_number(client: &OnlineClient<SubstrateConfig>, block_hash: H256) -> Option<u32> {
+    match client.blocks().at(block_hash).await {
+        Ok(block) => Some(block.number()),
+        Err(e) => {
+            warn!("Failed to fetch block {}: {:?}", block_hash, e);
+            None
+        }
+    }
+}
+
+/// Formats an optional block number for logging
+fn describe_block(block: Option<u32>) -> String {
+    block.map_or_else(|| "unknown".to_string(), |number| number.to_string())
+}
+
+/// Formats how many blocks after the target a transaction landed
+fn describe_delay(block: Option<u32>, target_block: u32) -> String {
+    match block {
+        Some(number) => format!("{} blocks", number.saturating_sub(target_block)),
+        None => "unknown blocks".to_string(),
+    }
+}